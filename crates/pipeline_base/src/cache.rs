@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use crate::{Clock, SystemClock};
+
+/// Values keyed by target, evicted after being idle for `idle_timeout`.
+///
+/// An idle entry is never handed out. Idle entries are swept lazily when the cache is accessed, at most once per
+/// `idle_timeout`, so a lookup does not scan the whole cache.
+#[derive(Debug)]
+pub struct Cache<K, V, C = SystemClock> {
+    entries: HashMap<K, Entry<V>>,
+    idle_timeout: Duration,
+    clock: C,
+    last_evicted: Instant,
+}
+
+#[derive(Debug)]
+struct Entry<V> {
+    value: V,
+    last_used: Instant,
+}
+
+impl<K, V, C> Cache<K, V, C>
+where
+    K: Hash + Eq,
+    V: Clone,
+    C: Clock,
+{
+    pub fn new(idle_timeout: Duration, clock: C) -> Self {
+        let last_evicted = clock.now();
+        Self {
+            entries: HashMap::new(),
+            idle_timeout,
+            clock,
+            last_evicted,
+        }
+    }
+
+    /// Get a clone of the value cached for `key` and reset its idle timer.
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let now = self.evict();
+        let entry = self.entries.get_mut(key)?;
+        if now.saturating_duration_since(entry.last_used) >= self.idle_timeout {
+            self.entries.remove(key);
+            return None;
+        }
+        entry.last_used = now;
+        Some(entry.value.clone())
+    }
+
+    /// Get a clone of the value cached for `key`, or cache `value` for `key` and return it if there is none.
+    pub fn get_or_insert(&mut self, key: K, value: V) -> V {
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        self.insert(key, value.clone());
+        value
+    }
+
+    /// Cache `value` for `key`, replacing the previous value if any.
+    pub fn insert(&mut self, key: K, value: V) {
+        let now = self.evict();
+        let entry = Entry {
+            value,
+            last_used: now,
+        };
+        self.entries.insert(key, entry);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Evict all idle entries if `idle_timeout` has passed since the last sweep and return the current time.
+    fn evict(&mut self) -> Instant {
+        let now = self.clock.now();
+        let idle_timeout = self.idle_timeout;
+        if now.saturating_duration_since(self.last_evicted) < idle_timeout {
+            return now;
+        }
+        self.last_evicted = now;
+        self.entries
+            .retain(|_, entry| now.saturating_duration_since(entry.last_used) < idle_timeout);
+        now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ManualClock;
+
+    #[test]
+    fn test_cache_idle_eviction() {
+        let clock = ManualClock::new();
+        let mut cache = Cache::new(Duration::from_secs(10), clock.clone());
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.len(), 2);
+
+        // Keep "a" alive.
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        // "b" has been idle for too long.
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.len(), 1);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.get(&"a"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_cache_sweeps_once_per_idle_timeout() {
+        let clock = ManualClock::new();
+        let mut cache = Cache::new(Duration::from_secs(10), clock.clone());
+
+        clock.advance(Duration::from_secs(9));
+        cache.insert("a", 1);
+        // The first sweep is due.
+        clock.advance(Duration::from_secs(2));
+        cache.insert("b", 2);
+        assert_eq!(cache.len(), 2);
+
+        // "a" is idle but no sweep is due yet; it is still never handed out.
+        clock.advance(Duration::from_secs(9));
+        assert_eq!(cache.get(&"b"), Some(2));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"a"), None);
+        assert_eq!(cache.len(), 1);
+
+        // The next sweep evicts every idle entry.
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(cache.get(&"x"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_cache_get_or_insert() {
+        let mut cache = Cache::new(Duration::from_secs(10), ManualClock::new());
+        assert_eq!(cache.get_or_insert("a", 1), 1);
+        assert_eq!(cache.get_or_insert("a", 2), 1);
+        assert_eq!(cache.len(), 1);
+    }
+}
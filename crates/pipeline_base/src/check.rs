@@ -0,0 +1,32 @@
+/// A stack that is a `Service` of `Req`.
+///
+/// Implemented for every such `Service`; it only exists so the `check_*` helpers fail with an error naming the stack.
+#[diagnostic::on_unimplemented(
+    message = "the stack `{Self}` does not accept `{Req}`",
+    label = "this stack does not accept `{Req}`",
+    note = "the outermost type is the layer pushed last; check the type parameters it was pushed with"
+)]
+pub trait ServiceFor<Req>: tower::Service<Req> {}
+impl<S, Req> ServiceFor<Req> for S where S: tower::Service<Req> {}
+
+/// A stack that can be sent to and shared with other threads.
+///
+/// Implemented for every `Send + Sync` type; it only exists so `Stack::check_send_sync` fails with an error naming the stack.
+#[diagnostic::on_unimplemented(
+    message = "the stack `{Self}` is not `Send + Sync`",
+    label = "this stack is not `Send + Sync`",
+    note = "the outermost type is the layer pushed last; one of the layers or the inner service holds a type that cannot cross threads"
+)]
+pub trait SendSyncStack: Send + Sync {}
+impl<S: Send + Sync> SendSyncStack for S {}
+
+/// A stack that is `Unpin`.
+///
+/// Implemented for every `Unpin` type; it only exists so the `check_unpin` helpers fail with an error naming the stack.
+#[diagnostic::on_unimplemented(
+    message = "the stack `{Self}` is not `Unpin`",
+    label = "this stack is not `Unpin`",
+    note = "the outermost type is the layer pushed last; one of the layers or the inner service must be pinned"
+)]
+pub trait UnpinStack: Unpin {}
+impl<S: Unpin> UnpinStack for S {}
@@ -0,0 +1,47 @@
+use std::time::Instant;
+
+/// A source of the current time.
+///
+/// Time-driven layers take a `Clock` so tests can advance time without sleeping.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// Reads the time from the operating system.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when it is advanced.
+///
+/// Clones share the same time.
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Clone, Debug)]
+pub struct ManualClock(std::rc::Rc<std::cell::Cell<Instant>>);
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+    /// Start at the current time.
+    pub fn new() -> Self {
+        Self(std::rc::Rc::new(std::cell::Cell::new(Instant::now())))
+    }
+
+    pub fn advance(&self, duration: std::time::Duration) {
+        self.0.set(self.0.get() + duration);
+    }
+}
+#[cfg(any(test, feature = "test-util"))]
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
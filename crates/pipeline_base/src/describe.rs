@@ -0,0 +1,206 @@
+use std::{any::type_name, borrow::Cow, fmt};
+
+/// Something that can tell what it is composed of at runtime.
+pub trait Describe {
+    fn describe(&self) -> Description;
+}
+
+/// The composition of a stack.
+///
+/// The layers are listed from the bottom to the top, the same order they are pushed in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Description {
+    layers: Vec<Node>,
+}
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Node {
+    name: Cow<'static, str>,
+    /// Other stacks the layer dispatches to besides the layers below it
+    branches: Vec<Description>,
+}
+impl Description {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describe a single service or layer by its type.
+    pub fn of<T: ?Sized>() -> Self {
+        let mut description = Self::new();
+        description.push(short_type_name::<T>());
+        description
+    }
+
+    /// Record a layer on top of the described ones.
+    pub fn push(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.push_branched(name, Vec::new());
+    }
+
+    /// Record a layer that also dispatches to the `branches`.
+    pub fn push_branched(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        branches: Vec<Description>,
+    ) {
+        self.layers.push(Node {
+            name: name.into(),
+            branches,
+        });
+    }
+
+    /// Record the layers of `other` on top of the described ones.
+    pub fn extend(&mut self, other: Description) {
+        self.layers.extend(other.layers);
+    }
+
+    /// The names of the layers from the bottom to the top.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|node| node.name.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Render the description as a Graphviz DOT graph.
+    ///
+    /// Edges point in the direction requests flow, from the outer layers to the inner ones.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph stack {\n");
+        let mut next_id = 0;
+        self.write_dot(&mut out, &mut next_id);
+        out.push_str("}\n");
+        out
+    }
+
+    /// Write the nodes and edges and return the id of the top layer.
+    fn write_dot(&self, out: &mut String, next_id: &mut usize) -> Option<usize> {
+        let mut below = None;
+        for node in &self.layers {
+            let id = *next_id;
+            *next_id += 1;
+            let label = node.name.replace('\\', "\\\\").replace('"', "\\\"");
+            out.push_str(&format!("    n{id} [label=\"{label}\"];\n"));
+            if let Some(below) = below {
+                out.push_str(&format!("    n{id} -> n{below};\n"));
+            }
+            for branch in &node.branches {
+                if let Some(top) = branch.write_dot(out, next_id) {
+                    out.push_str(&format!("    n{id} -> n{top};\n"));
+                }
+            }
+            below = Some(id);
+        }
+        below
+    }
+}
+/// A textual tree with one layer per line, from the bottom to the top.
+///
+/// The branches of a layer are indented below it.
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for node in &self.layers {
+            writeln!(f, "{}", node.name)?;
+            for (i, branch) in node.branches.iter().enumerate() {
+                let last = i + 1 == node.branches.len();
+                let (first, rest) = if last {
+                    ("└── ", "    ")
+                } else {
+                    ("├── ", "│   ")
+                };
+                for (j, line) in branch.to_string().lines().enumerate() {
+                    let head = if j == 0 { first } else { rest };
+                    writeln!(f, "{head}{line}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The type name of `T` without module paths.
+pub(crate) fn short_type_name<T: ?Sized>() -> String {
+    let full = type_name::<T>();
+    let mut out = String::with_capacity(full.len());
+    let mut path_start = 0;
+    let mut chars = full.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            out.truncate(path_start);
+            continue;
+        }
+        out.push(c);
+        if !(c.is_alphanumeric() || c == '_') {
+            path_start = out.len();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod a {
+        pub struct Outer<T>(pub T);
+        pub mod b {
+            pub struct Inner;
+        }
+    }
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name::<a::Outer<a::b::Inner>>(), "Outer<Inner>");
+        assert_eq!(
+            short_type_name::<(a::b::Inner, Vec<String>)>(),
+            "(Inner, Vec<String>)"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut branch = Description::new();
+        branch.push("MakeUpper");
+        branch.push("Filter");
+
+        let mut description = Description::new();
+        description.push("MakeEcho");
+        description.push_branched("MakeSwitch", vec![branch]);
+        description.push("BoxMakeService");
+        assert_eq!(
+            description.names().collect::<Vec<_>>(),
+            ["MakeEcho", "MakeSwitch", "BoxMakeService"]
+        );
+
+        assert_eq!(
+            description.to_string(),
+            "\
+MakeEcho
+MakeSwitch
+└── MakeUpper
+    Filter
+BoxMakeService
+"
+        );
+        assert_eq!(
+            description.to_dot(),
+            "\
+digraph stack {
+    n0 [label=\"MakeEcho\"];
+    n1 [label=\"MakeSwitch\"];
+    n1 -> n0;
+    n2 [label=\"MakeUpper\"];
+    n3 [label=\"Filter\"];
+    n3 -> n2;
+    n1 -> n3;
+    n4 [label=\"BoxMakeService\"];
+    n4 -> n1;
+}
+"
+        );
+    }
+}
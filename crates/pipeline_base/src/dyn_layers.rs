@@ -0,0 +1,239 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use tower::{util::BoxService, Layer, Service};
+
+use crate::{Describe, Description, Error, MapErrBoxed, Stack};
+
+/// The type-erased service the layers of `DynLayers<S, Req>` wrap and produce.
+pub type DynService<S, Req> = BoxService<Req, <S as Service<Req>>::Response, Error>;
+
+type BoxLayer<S, Req> =
+    Box<dyn Layer<DynService<S, Req>, Service = DynService<S, Req>> + Send + Sync>;
+
+/// Layers chosen at runtime, such as from configuration.
+///
+/// Like `Layers`, the execution order is from the bottom to the top. The errors of the inner service are boxed once at the bottom; every layer then wraps a type-erased service, must keep the response type and the boxed `Error`, and is erased itself with a single `BoxService`.
+///
+/// `S`: the service the layers are applied to
+pub struct DynLayers<S, Req>
+where
+    S: Service<Req>,
+{
+    layers: Vec<(Cow<'static, str>, BoxLayer<S, Req>)>,
+    _service: PhantomData<fn(S, Req)>,
+}
+impl<S, Req> DynLayers<S, Req>
+where
+    S: Service<Req>,
+    S::Response: 'static,
+    Req: 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            _service: PhantomData,
+        }
+    }
+
+    /// Push an outer layer named `name`.
+    pub fn push<L>(&mut self, name: impl Into<Cow<'static, str>>, layer: L)
+    where
+        L: Layer<DynService<S, Req>> + Send + Sync + 'static,
+        L::Service: Service<Req, Response = S::Response, Error = Error> + Send + 'static,
+        <L::Service as Service<Req>>::Future: Send + 'static,
+    {
+        self.layers
+            .push((name.into(), box_layer::<S, Req, _>(layer)));
+    }
+
+    /// Insert a layer named `name` right below the layer named `anchor`, so it sees the requests after `anchor` does.
+    ///
+    /// Returns `false` and leaves the layers unchanged if no layer is named `anchor`.
+    pub fn insert_below<L>(
+        &mut self,
+        anchor: &str,
+        name: impl Into<Cow<'static, str>>,
+        layer: L,
+    ) -> bool
+    where
+        L: Layer<DynService<S, Req>> + Send + Sync + 'static,
+        L::Service: Service<Req, Response = S::Response, Error = Error> + Send + 'static,
+        <L::Service as Service<Req>>::Future: Send + 'static,
+    {
+        let Some(index) = self.position(anchor) else {
+            return false;
+        };
+        self.layers
+            .insert(index, (name.into(), box_layer::<S, Req, _>(layer)));
+        true
+    }
+
+    /// Insert a layer named `name` right above the layer named `anchor`, so it sees the requests before `anchor` does.
+    ///
+    /// Returns `false` and leaves the layers unchanged if no layer is named `anchor`.
+    pub fn insert_above<L>(
+        &mut self,
+        anchor: &str,
+        name: impl Into<Cow<'static, str>>,
+        layer: L,
+    ) -> bool
+    where
+        L: Layer<DynService<S, Req>> + Send + Sync + 'static,
+        L::Service: Service<Req, Response = S::Response, Error = Error> + Send + 'static,
+        <L::Service as Service<Req>>::Future: Send + 'static,
+    {
+        let Some(index) = self.position(anchor) else {
+            return false;
+        };
+        self.layers
+            .insert(index + 1, (name.into(), box_layer::<S, Req, _>(layer)));
+        true
+    }
+
+    /// Remove the lowest layer named `name`.
+    ///
+    /// Returns `false` if no layer is named `name`.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let Some(index) = self.position(name) else {
+            return false;
+        };
+        self.layers.remove(index);
+        true
+    }
+
+    /// The names of the layers from the bottom to the top.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|(name, _)| name.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.names().position(|n| n == name)
+    }
+}
+impl<S, Req> Default for DynLayers<S, Req>
+where
+    S: Service<Req>,
+    S::Response: 'static,
+    Req: 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, Req> Describe for DynLayers<S, Req>
+where
+    S: Service<Req>,
+{
+    fn describe(&self) -> Description {
+        let mut description = Description::new();
+        for (name, _) in &self.layers {
+            description.push(name.clone());
+        }
+        description
+    }
+}
+
+impl<S, Req> Layer<S> for DynLayers<S, Req>
+where
+    S: Service<Req> + Send + 'static,
+    S::Error: Into<Error>,
+    S::Future: Send + 'static,
+    Req: 'static,
+{
+    type Service = DynService<S, Req>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let inner = BoxService::new(MapErrBoxed::new(inner));
+        self.layers
+            .iter()
+            .fold(inner, |inner, (_, layer)| layer.layer(inner))
+    }
+}
+
+/// Erase the type of the layer and of the services it produces.
+///
+/// The errors are already boxed, so the produced service is boxed as is.
+fn box_layer<S, Req, L>(layer: L) -> BoxLayer<S, Req>
+where
+    S: Service<Req>,
+    S::Response: 'static,
+    Req: 'static,
+    L: Layer<DynService<S, Req>> + Send + Sync + 'static,
+    L::Service: Service<Req, Response = S::Response, Error = Error> + Send + 'static,
+    <L::Service as Service<Req>>::Future: Send + 'static,
+{
+    Box::new(tower::layer::layer_fn(move |inner| {
+        BoxService::new(layer.layer(inner))
+    }))
+}
+
+impl<S> Stack<S> {
+    /// Push all the layers onto the stack, in the order they were pushed onto `layers`.
+    pub fn push_dyn_layers<Req>(self, layers: &DynLayers<S, Req>) -> Stack<DynService<S, Req>>
+    where
+        S: Service<Req> + Send + 'static,
+        S::Error: Into<Error>,
+        S::Future: Send + 'static,
+        Req: 'static,
+    {
+        let (inner, description) = self.into_parts();
+        let description = description.map(|mut description| {
+            description.extend(layers.describe());
+            description
+        });
+        Stack::from_parts(layers.layer(inner), description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::pin};
+
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService, TraceBody, TraceLayer};
+
+    use super::*;
+
+    #[test]
+    fn test_dyn_layers() {
+        // Build the layers from a runtime list.
+        let mut layers = DynLayers::<EchoService, TraceBody>::new();
+        for mark in ["1", "2", "3"] {
+            layers.push(mark, TraceLayer::new(mark, mark));
+        }
+        assert!(layers.remove("2"));
+        assert!(!layers.remove("2"));
+        assert!(layers.insert_above("1", "above_1", TraceLayer::new("above_1", "above_1")));
+        assert!(layers.insert_below("1", "below_1", TraceLayer::new("below_1", "below_1")));
+        assert!(!layers.insert_below("2", "below_2", TraceLayer::new("below_2", "below_2")));
+        assert_eq!(
+            layers.names().collect::<Vec<_>>(),
+            ["below_1", "1", "above_1", "3"]
+        );
+
+        // Apply the layers.
+        let stack = Stack::described(EchoService).push_dyn_layers(&layers);
+        assert_eq!(
+            stack.describe().names().collect::<Vec<_>>(),
+            ["EchoService", "below_1", "1", "above_1", "3"]
+        );
+        let mut svc = stack.into_inner();
+
+        // Call the service.
+        let cx = &mut noop_context();
+        assert_ready_ok!(svc.poll_ready(cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(
+            resp.history,
+            ["3", "above_1", "1", "below_1", "below_1", "1", "above_1", "3"]
+        );
+    }
+}
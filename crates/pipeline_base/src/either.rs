@@ -0,0 +1,142 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use crate::Error;
+
+/// One of two services, or one of two layers.
+///
+/// Both services must have the same response type. Their errors are boxed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Either<A, B> {
+    A(A),
+    B(B),
+}
+impl<A, B, Req> Service<Req> for Either<A, B>
+where
+    A: Service<Req>,
+    A::Error: Into<Error>,
+    B: Service<Req, Response = A::Response>,
+    B::Error: Into<Error>,
+{
+    type Response = A::Response;
+    type Error = Error;
+    type Future = EitherFuture<A::Future, B::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            Either::A(a) => a.poll_ready(cx).map_err(Into::into),
+            Either::B(b) => b.poll_ready(cx).map_err(Into::into),
+        }
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        match self {
+            Either::A(a) => EitherFuture::A {
+                future: a.call(req),
+            },
+            Either::B(b) => EitherFuture::B {
+                future: b.call(req),
+            },
+        }
+    }
+}
+
+/// Applies whichever layer is present.
+impl<A, B, S> Layer<S> for Either<A, B>
+where
+    A: Layer<S>,
+    B: Layer<S>,
+{
+    type Service = Either<A::Service, B::Service>;
+    fn layer(&self, inner: S) -> Self::Service {
+        match self {
+            Either::A(a) => Either::A(a.layer(inner)),
+            Either::B(b) => Either::B(b.layer(inner)),
+        }
+    }
+}
+
+pin_project! {
+    #[project = EitherFutureProj]
+    pub enum EitherFuture<A, B> {
+        A { #[pin] future: A },
+        B { #[pin] future: B },
+    }
+}
+impl<A, B, T, EA, EB> Future for EitherFuture<A, B>
+where
+    A: Future<Output = Result<T, EA>>,
+    EA: Into<Error>,
+    B: Future<Output = Result<T, EB>>,
+    EB: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            EitherFutureProj::A { future } => future.poll(cx).map_err(Into::into),
+            EitherFutureProj::B { future } => future.poll(cx).map_err(Into::into),
+        }
+    }
+}
+
+/// Chooses one of two inner targets for a target.
+pub trait Switch<Tgt> {
+    /// The target type of the first branch
+    type A;
+    /// The target type of the second branch
+    type B;
+
+    fn switch(&self, target: Tgt) -> Either<Self::A, Self::B>;
+}
+impl<F, Tgt, A, B> Switch<Tgt> for F
+where
+    F: Fn(Tgt) -> Either<A, B>,
+{
+    type A = A;
+    type B = B;
+    fn switch(&self, target: Tgt) -> Either<Self::A, Self::B> {
+        self(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::{ready, Ready},
+        pin::pin,
+    };
+
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+
+    use super::*;
+
+    struct UpperService;
+    impl Service<String> for UpperService {
+        type Response = String;
+        type Error = Error;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: String) -> Self::Future {
+            ready(Ok(req.to_uppercase()))
+        }
+    }
+
+    #[test]
+    fn test_either() {
+        let services: [Either<EchoService, UpperService>; 2] =
+            [Either::A(EchoService), Either::B(UpperService)];
+        let expected = ["hello", "HELLO"];
+        let cx = &mut noop_context();
+        for (mut service, expected) in services.into_iter().zip(expected) {
+            assert_ready_ok!(Service::<String>::poll_ready(&mut service, cx));
+            let resp = assert_ready_ok!(pin!(service.call("hello".to_string())).poll(cx));
+            assert_eq!(resp, expected);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+/// The error type of stacks with heterogeneous layers.
+///
+/// Layers that box their errors, such as `MapErrBoxed`, use this type.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Walk the source chain of `error`, starting with `error` itself, and return the first cause of type `E`.
+pub fn find_cause<'a, E>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a E>
+where
+    E: std::error::Error + 'static,
+{
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if let Some(cause) = error.downcast_ref::<E>() {
+            return Some(cause);
+        }
+        next = error.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Wrapped(Error);
+    impl fmt::Display for Wrapped {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "wrapped")
+        }
+    }
+    impl std::error::Error for Wrapped {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&*self.0)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Root(u8);
+    impl fmt::Display for Root {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "root {}", self.0)
+        }
+    }
+    impl std::error::Error for Root {}
+
+    #[test]
+    fn test_find_cause() {
+        let error: Error = Box::new(Wrapped(Box::new(Wrapped(Box::new(Root(7))))));
+        assert_eq!(find_cause::<Root>(&*error), Some(&Root(7)));
+        assert!(find_cause::<Wrapped>(&*error).is_some());
+
+        let error: Error = Box::new(Root(1));
+        assert_eq!(find_cause::<Root>(&*error), Some(&Root(1)));
+        assert!(find_cause::<Wrapped>(&*error).is_none());
+    }
+}
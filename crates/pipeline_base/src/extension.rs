@@ -0,0 +1,33 @@
+use crate::InsertParam;
+
+/// Inserts parameters into the extensions of `http::Request`s.
+///
+/// A parameter already in the extensions is replaced.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InsertExtension;
+impl<P, B> InsertParam<P, http::Request<B>> for InsertExtension
+where
+    P: Clone + Send + Sync + 'static,
+{
+    type Target = http::Request<B>;
+    fn insert_param(&self, param: P, mut req: http::Request<B>) -> Self::Target {
+        req.extensions_mut().insert(param);
+        req
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Addr(&'static str);
+
+    #[test]
+    fn test_insert_extension() {
+        let req = http::Request::new(());
+        let req = InsertExtension.insert_param(Addr("10.0.0.1:80"), req);
+        let req = InsertExtension.insert_param(Addr("10.0.0.2:80"), req);
+        assert_eq!(req.extensions().get::<Addr>(), Some(&Addr("10.0.0.2:80")));
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use tokio::time::{sleep, Sleep};
+use tower::{Layer, Service};
+
+use crate::{Error, Stack};
+
+/// Fails requests immediately while the inner service stays unready for longer than the timeout.
+///
+/// While failing fast, the service is always ready so callers are not held back. It leaves the fail-fast state as soon as the inner service becomes ready again.
+pub struct FailFast<S> {
+    inner: S,
+    timeout: Duration,
+    state: FailFastState,
+}
+enum FailFastState {
+    Open,
+    Waiting(Pin<Box<Sleep>>),
+    FailFast,
+}
+impl<S> FailFast<S> {
+    pub fn new(inner: S, timeout: Duration) -> Self {
+        Self {
+            inner,
+            timeout,
+            state: FailFastState::Open,
+        }
+    }
+
+    /// Whether requests are currently failed fast.
+    pub fn is_failfast(&self) -> bool {
+        matches!(self.state, FailFastState::FailFast)
+    }
+}
+/// Clones start in the open state.
+impl<S: Clone> Clone for FailFast<S> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.timeout)
+    }
+}
+impl<S: fmt::Debug> fmt::Debug for FailFast<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FailFast")
+            .field("inner", &self.inner)
+            .field("timeout", &self.timeout)
+            .field("failfast", &self.is_failfast())
+            .finish()
+    }
+}
+impl<S, Req> Service<Req> for FailFast<S>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = FailFastFuture<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if let Poll::Ready(res) = self.inner.poll_ready(cx) {
+            self.state = FailFastState::Open;
+            return Poll::Ready(res.map_err(Into::into));
+        }
+        loop {
+            self.state = match &mut self.state {
+                FailFastState::Open => FailFastState::Waiting(Box::pin(sleep(self.timeout))),
+                FailFastState::Waiting(timer) => match timer.as_mut().poll(cx) {
+                    Poll::Ready(()) => FailFastState::FailFast,
+                    Poll::Pending => return Poll::Pending,
+                },
+                FailFastState::FailFast => return Poll::Ready(Ok(())),
+            };
+        }
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        if self.is_failfast() {
+            return FailFastFuture::FailFast;
+        }
+        FailFastFuture::Inner {
+            future: self.inner.call(req),
+        }
+    }
+}
+
+pin_project! {
+    #[project = FailFastFutureProj]
+    pub enum FailFastFuture<F> {
+        Inner { #[pin] future: F },
+        FailFast,
+    }
+}
+impl<F, T, E> Future for FailFastFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            FailFastFutureProj::Inner { future } => future.poll(cx).map_err(Into::into),
+            FailFastFutureProj::FailFast => Poll::Ready(Err(FailFastError.into())),
+        }
+    }
+}
+
+/// The service stayed unready for longer than the fail-fast timeout.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FailFastError;
+impl fmt::Display for FailFastError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "service in fail-fast")
+    }
+}
+impl std::error::Error for FailFastError {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FailFastLayer(Duration);
+impl FailFastLayer {
+    pub fn new(timeout: Duration) -> Self {
+        Self(timeout)
+    }
+}
+impl<S> Layer<S> for FailFastLayer {
+    type Service = FailFast<S>;
+    fn layer(&self, inner: S) -> Self::Service {
+        FailFast::new(inner, self.0)
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that fails requests fast once the current stack stays unready for `timeout`.
+    pub fn push_failfast(self, timeout: Duration) -> Stack<FailFast<S>> {
+        self.push(FailFastLayer::new(timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        future::{poll_fn, ready, Ready},
+        rc::Rc,
+    };
+
+    use pipeline_test::{assert_pending, noop_context};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Echoes the request once it is open.
+    #[derive(Clone, Debug)]
+    struct GatedService(Rc<Cell<bool>>);
+    impl Service<&'static str> for GatedService {
+        type Response = &'static str;
+        type Error = Error;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.0.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            ready(Ok(req))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failfast() {
+        let open = Rc::new(Cell::new(true));
+        let stack = Stack::new(GatedService(open.clone()))
+            .push_failfast(Duration::from_secs(1))
+            .check_clone();
+        let mut service = stack.into_inner();
+
+        let resp = service.ready().await.unwrap().call("a").await.unwrap();
+        assert_eq!(resp, "a");
+
+        // The service is not ready before the timeout.
+        open.set(false);
+        let cx = &mut noop_context();
+        assert_pending!(service.poll_ready(cx));
+        tokio::time::advance(Duration::from_millis(999)).await;
+        assert_pending!(service.poll_ready(cx));
+        assert!(!service.is_failfast());
+
+        // Requests fail fast after the timeout.
+        tokio::time::advance(Duration::from_millis(1)).await;
+        for req in ["b", "c"] {
+            poll_fn(|cx| service.poll_ready(cx)).await.unwrap();
+            let error = service.call(req).await.unwrap_err();
+            assert!(error.is::<FailFastError>());
+        }
+
+        // The service recovers once the inner service is ready.
+        open.set(true);
+        let resp = service.ready().await.unwrap().call("d").await.unwrap();
+        assert_eq!(resp, "d");
+        assert!(!service.is_failfast());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use crate::{Error, Stack};
+
+/// Checks a target before it is handed to the inner service.
+///
+/// The target is either converted into the inner target or rejected with an error.
+pub trait Predicate<Tgt> {
+    /// The target type of the inner service
+    type Target;
+
+    fn check(&mut self, target: Tgt) -> Result<Self::Target, Error>;
+}
+impl<F, Tgt, InnerTgt, E> Predicate<Tgt> for F
+where
+    F: FnMut(Tgt) -> Result<InnerTgt, E>,
+    E: Into<Error>,
+{
+    type Target = InnerTgt;
+    fn check(&mut self, target: Tgt) -> Result<Self::Target, Error> {
+        self(target).map_err(Into::into)
+    }
+}
+
+/// Rejects targets that fail the predicate without calling the inner service.
+///
+/// Errors from both the predicate and the inner service are boxed.
+#[derive(Clone, Debug)]
+pub struct Filter<S, P> {
+    inner: S,
+    predicate: P,
+}
+impl<S, P> Filter<S, P> {
+    pub fn new(inner: S, predicate: P) -> Self {
+        Self { inner, predicate }
+    }
+}
+impl<S, P, Tgt> Service<Tgt> for Filter<S, P>
+where
+    P: Predicate<Tgt>,
+    S: Service<P::Target>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = FilterFuture<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        match self.predicate.check(target) {
+            Ok(target) => FilterFuture::Inner {
+                future: self.inner.call(target),
+            },
+            Err(error) => FilterFuture::Rejected { error: Some(error) },
+        }
+    }
+}
+
+pin_project! {
+    #[project = FilterFutureProj]
+    pub enum FilterFuture<F> {
+        Inner { #[pin] future: F },
+        Rejected { error: Option<Error> },
+    }
+}
+impl<F, T, E> Future for FilterFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            FilterFutureProj::Inner { future } => future.poll(cx).map_err(Into::into),
+            FilterFutureProj::Rejected { error } => {
+                let error = error.take().expect("polled after completion");
+                Poll::Ready(Err(error))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FilterLayer<P>(P);
+impl<P> FilterLayer<P> {
+    pub fn new(predicate: P) -> Self {
+        Self(predicate)
+    }
+}
+impl<S, P> Layer<S> for FilterLayer<P>
+where
+    P: Clone,
+{
+    type Service = Filter<S, P>;
+    fn layer(&self, inner: S) -> Self::Service {
+        Filter::new(inner, self.0.clone())
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that checks the target before it reaches the current stack.
+    ///
+    /// Rejected targets never reach the current stack.
+    pub fn push_filter<P>(self, predicate: P) -> Stack<Filter<S, P>>
+    where
+        P: Clone,
+    {
+        self.push(FilterLayer::new(predicate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        fmt,
+        future::{ready, Ready},
+        pin::pin,
+        rc::Rc,
+    };
+
+    use pipeline_test::{assert_ready_err, assert_ready_ok, noop_context};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct TooLong(usize);
+    impl fmt::Display for TooLong {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "target is {} bytes long", self.0)
+        }
+    }
+    impl std::error::Error for TooLong {}
+
+    /// Echoes the request and counts the calls.
+    #[derive(Clone, Debug)]
+    struct CountService(Rc<Cell<usize>>);
+    impl<Req> Service<Req> for CountService {
+        type Response = Req;
+        type Error = Error;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: Req) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            ready(Ok(req))
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let calls = Rc::new(Cell::new(0));
+
+        // Build a stack.
+        let stack = Stack::new(CountService(calls.clone())).push_filter(|target: &'static str| {
+            if target.len() > 5 {
+                return Err(TooLong(target.len()));
+            }
+            Ok(target.len())
+        });
+        let mut service = stack.into_inner();
+
+        // Accept a target.
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<&str>::poll_ready(&mut service, cx));
+        let resp = assert_ready_ok!(pin!(service.call("hello")).poll(cx));
+        assert_eq!(resp, 5);
+        assert_eq!(calls.get(), 1);
+
+        // Reject a target.
+        let error = assert_ready_err!(pin!(service.call("goodbye")).poll(cx));
+        let error = error.downcast::<TooLong>().unwrap();
+        assert_eq!(error.0, 7);
+        assert_eq!(calls.get(), 1);
+    }
+}
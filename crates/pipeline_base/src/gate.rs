@@ -0,0 +1,292 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tokio::sync::{
+    watch::{self, error::RecvError},
+    OwnedSemaphorePermit, Semaphore,
+};
+use tokio_util::sync::{PollSemaphore, ReusableBoxFuture};
+use tower::{Layer, Service};
+
+use crate::{Error, Stack};
+
+/// Whether a `Gate` lets requests through.
+#[derive(Clone, Debug)]
+pub enum GateState {
+    Open,
+    Closed,
+    /// Each request holds a permit of the semaphore until its response is ready.
+    Limited(Arc<Semaphore>),
+}
+
+/// Controls the `Gate`s created from it.
+#[derive(Clone, Debug)]
+pub struct GateHandle(Arc<watch::Sender<GateState>>);
+impl GateHandle {
+    /// Create a handle in the open state.
+    pub fn new() -> Self {
+        let (tx, _) = watch::channel(GateState::Open);
+        Self(Arc::new(tx))
+    }
+
+    pub fn open(&self) {
+        self.0.send_replace(GateState::Open);
+    }
+
+    pub fn close(&self) {
+        self.0.send_replace(GateState::Closed);
+    }
+
+    pub fn limit(&self, semaphore: Arc<Semaphore>) {
+        self.0.send_replace(GateState::Limited(semaphore));
+    }
+
+    pub fn state(&self) -> GateState {
+        self.0.borrow().clone()
+    }
+}
+impl Default for GateHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type Changed = (Result<(), RecvError>, watch::Receiver<GateState>);
+
+async fn changed(mut rx: watch::Receiver<GateState>) -> Changed {
+    let res = rx.changed().await;
+    (res, rx)
+}
+
+/// Holds back requests to the inner service while its `GateHandle` is closed or out of permits.
+///
+/// The gate keeps the last state if all its handles are dropped. Requests fail with `GateClosed` while the semaphore of a limited gate is closed.
+pub struct Gate<S> {
+    inner: S,
+    state: Admit,
+    permit: Option<OwnedSemaphorePermit>,
+    changed: Option<ReusableBoxFuture<'static, Changed>>,
+}
+enum Admit {
+    Open,
+    Closed,
+    Limited(PollSemaphore),
+}
+impl<S> Gate<S> {
+    pub fn new(inner: S, handle: &GateHandle) -> Self {
+        let mut rx = handle.0.subscribe();
+        let state = Admit::new(rx.borrow_and_update().clone());
+        Self {
+            inner,
+            state,
+            permit: None,
+            changed: Some(ReusableBoxFuture::new(changed(rx))),
+        }
+    }
+
+    /// Pick up the state changes of the handle.
+    fn poll_changed(&mut self, cx: &mut Context<'_>) {
+        let Some(future) = &mut self.changed else {
+            return;
+        };
+        while let Poll::Ready((res, mut rx)) = future.poll(cx) {
+            if res.is_err() {
+                self.changed = None;
+                return;
+            }
+            self.state = Admit::new(rx.borrow_and_update().clone());
+            self.permit = None;
+            future.set(changed(rx));
+        }
+    }
+}
+impl Admit {
+    fn new(state: GateState) -> Self {
+        match state {
+            GateState::Open => Admit::Open,
+            GateState::Closed => Admit::Closed,
+            GateState::Limited(semaphore) => Admit::Limited(PollSemaphore::new(semaphore)),
+        }
+    }
+}
+impl<S: fmt::Debug> fmt::Debug for Gate<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            Admit::Open => "Open",
+            Admit::Closed => "Closed",
+            Admit::Limited(_) => "Limited",
+        };
+        f.debug_struct("Gate")
+            .field("inner", &self.inner)
+            .field("state", &state)
+            .finish()
+    }
+}
+impl<S, Req> Service<Req> for Gate<S>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = GateFuture<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_changed(cx);
+        match &mut self.state {
+            Admit::Open => (),
+            Admit::Closed => return Poll::Pending,
+            Admit::Limited(semaphore) => {
+                if self.permit.is_none() {
+                    match semaphore.poll_acquire(cx) {
+                        Poll::Ready(Some(permit)) => self.permit = Some(permit),
+                        // A closed semaphore never hands out permits again.
+                        Poll::Ready(None) => return Poll::Ready(Err(GateClosed(()).into())),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+            }
+        }
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        GateFuture {
+            future: self.inner.call(req),
+            _permit: self.permit.take(),
+        }
+    }
+}
+
+pin_project! {
+    pub struct GateFuture<F> {
+        #[pin]
+        future: F,
+        _permit: Option<OwnedSemaphorePermit>,
+    }
+}
+impl<F, T, E> Future for GateFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().future.poll(cx).map_err(Into::into)
+    }
+}
+
+/// The semaphore of a limited gate was closed.
+#[derive(Debug)]
+pub struct GateClosed(());
+impl fmt::Display for GateClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "gate semaphore closed")
+    }
+}
+impl std::error::Error for GateClosed {}
+
+#[derive(Clone, Debug)]
+pub struct GateLayer(GateHandle);
+impl GateLayer {
+    pub fn new(handle: GateHandle) -> Self {
+        Self(handle)
+    }
+}
+impl<S> Layer<S> for GateLayer {
+    type Service = Gate<S>;
+    fn layer(&self, inner: S) -> Self::Service {
+        Gate::new(inner, &self.0)
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that holds back requests as told by `handle`.
+    pub fn push_gate(self, handle: GateHandle) -> Stack<Gate<S>> {
+        self.push(GateLayer::new(handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pipeline_test::{
+        assert_pending, assert_ready_err, assert_ready_ok, noop_context, EchoService,
+    };
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_gate() {
+        let handle = GateHandle::new();
+        let mut service = Stack::new(EchoService)
+            .push_gate(handle.clone())
+            .into_inner();
+        let cx = &mut noop_context();
+
+        // Open
+        let resp = ServiceExt::<&str>::ready(&mut service)
+            .await
+            .unwrap()
+            .call("a")
+            .await
+            .unwrap();
+        assert_eq!(resp, "a");
+
+        // Closed
+        handle.close();
+        assert_pending!(Service::<&str>::poll_ready(&mut service, cx));
+
+        // Reopened while a caller is waiting
+        let reopen = tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                handle.open();
+            }
+        });
+        let resp = ServiceExt::<&str>::ready(&mut service)
+            .await
+            .unwrap()
+            .call("b")
+            .await
+            .unwrap();
+        assert_eq!(resp, "b");
+        reopen.await.unwrap();
+
+        // Limited: a permit is held until the response is ready.
+        let semaphore = Arc::new(Semaphore::new(1));
+        handle.limit(semaphore.clone());
+        let fut = ServiceExt::<&str>::ready(&mut service)
+            .await
+            .unwrap()
+            .call("c");
+        assert_eq!(semaphore.available_permits(), 0);
+        assert_pending!(Service::<&str>::poll_ready(&mut service, cx));
+        assert_eq!(fut.await.unwrap(), "c");
+        assert_ready_ok!(Service::<&str>::poll_ready(&mut service, cx));
+        let resp = service.call("d").await.unwrap();
+        assert_eq!(resp, "d");
+
+        // The last state is kept once the handle is gone.
+        drop(handle);
+        let resp = ServiceExt::<&str>::ready(&mut service)
+            .await
+            .unwrap()
+            .call("e")
+            .await
+            .unwrap();
+        assert_eq!(resp, "e");
+
+        // A closed semaphore fails requests instead of holding them back forever.
+        semaphore.close();
+        let e = assert_ready_err!(Service::<&str>::poll_ready(&mut service, cx));
+        assert!(e.is::<GateClosed>());
+    }
+}
@@ -0,0 +1,99 @@
+use std::task::{Context, Poll};
+
+use tower::{Layer, Service};
+
+use crate::{InsertParam, Stack};
+
+/// Inserts a parameter of the target into every request before handing it to the inner service.
+///
+/// `P`: the parameter, cloned into every request
+///
+/// `X`: how the parameter is inserted into the request
+#[derive(Clone, Debug)]
+pub struct InsertTarget<S, P, X> {
+    inner: S,
+    param: P,
+    insert: X,
+}
+impl<S, P, X> InsertTarget<S, P, X> {
+    pub fn new(inner: S, param: P, insert: X) -> Self {
+        Self {
+            inner,
+            param,
+            insert,
+        }
+    }
+}
+impl<S, P, X, Req> Service<Req> for InsertTarget<S, P, X>
+where
+    P: Clone,
+    X: InsertParam<P, Req>,
+    S: Service<X::Target>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let req = self.insert.insert_param(self.param.clone(), req);
+        self.inner.call(req)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InsertTargetLayer<P, X> {
+    param: P,
+    insert: X,
+}
+impl<P, X> InsertTargetLayer<P, X> {
+    pub fn new(param: P, insert: X) -> Self {
+        Self { param, insert }
+    }
+}
+impl<S, P, X> Layer<S> for InsertTargetLayer<P, X>
+where
+    P: Clone,
+    X: Clone,
+{
+    type Service = InsertTarget<S, P, X>;
+    fn layer(&self, inner: S) -> Self::Service {
+        InsertTarget::new(inner, self.param.clone(), self.insert.clone())
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that inserts `param` into every request before it reaches the current stack.
+    ///
+    /// `insert`: how the parameter is inserted into the request
+    pub fn push_insert_target<P, X>(self, param: P, insert: X) -> Stack<InsertTarget<S, P, X>>
+    where
+        P: Clone,
+        X: Clone,
+    {
+        self.push(InsertTargetLayer::new(param, insert))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::pin};
+
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+
+    use super::*;
+
+    #[test]
+    fn test_insert_target() {
+        let stack = Stack::new(EchoService)
+            .push_insert_target("10.0.0.1:80", ())
+            .check_clone();
+        let mut service = stack.into_inner();
+
+        // Call the service.
+        let cx = &mut noop_context();
+        let resp = assert_ready_ok!(pin!(service.call("hello")).poll(cx));
+        assert_eq!(resp, ("10.0.0.1:80", "hello"));
+    }
+}
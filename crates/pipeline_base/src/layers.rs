@@ -1,163 +1,237 @@
-use tower::{layer::util::Identity, Layer};
+use std::borrow::Cow;
+
+use tower::{
+    layer::{layer_fn, util::Identity, LayerFn},
+    Layer, ServiceBuilder,
+};
+
+use crate::{
+    describe::{short_type_name, Description},
+    Describe, Either, Stack,
+};
 
 /// The same as `tower#ServiceBuilder` but with a upside-down execution order.
 ///
 /// The execution order is in line with `Stack`.
 ///
 /// The execution order is from the bottom to the top.
-pub struct Layers<L>(L);
+pub struct Layers<L> {
+    layers: L,
+    description: Description,
+}
 
 impl Layers<Identity> {
     pub fn new() -> Self {
-        Layers(Identity::new())
+        Layers {
+            layers: Identity::new(),
+            description: Description::new(),
+        }
     }
 }
 
 impl<L> Layers<L> {
-    /// Push an outer layer onto the layer stack.
-    pub fn push<O>(self, outer: O) -> Layers<tower::layer::util::Stack<L, O>> {
-        Layers(tower::layer::util::Stack::new(self.0, outer))
+    /// Take over the layers of a `ServiceBuilder`.
+    ///
+    /// The layers keep executing in the order they were added to the builder. They are described as a single `ServiceBuilder` layer.
+    pub fn from_service_builder(builder: ServiceBuilder<L>) -> Self {
+        let mut description = Description::new();
+        description.push("ServiceBuilder");
+        Layers {
+            layers: builder.into_inner(),
+            description,
+        }
     }
-}
 
-impl<S, L: Layer<S>> Layer<S> for Layers<L> {
-    type Service = L::Service;
+    /// Hand the layers over to a `ServiceBuilder`.
+    ///
+    /// The layers keep executing in the order they were pushed. Layers added to the builder afterwards are applied inside all of them.
+    pub fn into_service_builder(self) -> ServiceBuilder<tower::layer::util::Stack<L, Identity>> {
+        ServiceBuilder::new().layer(self.layers)
+    }
+}
 
-    fn layer(&self, inner: S) -> Self::Service {
-        self.0.layer(inner)
+impl Default for Layers<Identity> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use std::{
-        convert::Infallible,
-        future::{ready, Ready},
-        pin::Pin,
-        task::{Context, Poll},
-    };
+impl<L> Layers<L> {
+    /// Push an outer layer onto the layer stack.
+    ///
+    /// The layer is described by its type name.
+    pub fn push<O>(self, outer: O) -> Layers<tower::layer::util::Stack<L, O>> {
+        self.push_named(short_type_name::<O>(), outer)
+    }
 
-    use futures::{pin_mut, Future};
-    use tower::Service;
+    /// Push an outer layer onto the layer stack and describe it by `name`.
+    pub fn push_named<O>(
+        self,
+        name: impl Into<Cow<'static, str>>,
+        outer: O,
+    ) -> Layers<tower::layer::util::Stack<L, O>> {
+        let mut description = self.description;
+        description.push(name);
+        Layers {
+            layers: tower::layer::util::Stack::new(self.layers, outer),
+            description,
+        }
+    }
 
-    use super::*;
+    /// Push an outer layer if there is one.
+    ///
+    /// Without a layer, the services are still wrapped in `Either::B(Identity)`, so their error type is boxed into
+    /// `Error` either way.
+    pub fn push_option<O>(
+        self,
+        outer: Option<O>,
+    ) -> Layers<tower::layer::util::Stack<L, Either<O, Identity>>> {
+        match outer {
+            Some(outer) => self.push_named(short_type_name::<O>(), Either::A(outer)),
+            None => Layers {
+                layers: tower::layer::util::Stack::new(self.layers, Either::B(Identity::new())),
+                description: self.description,
+            },
+        }
+    }
 
-    trait Trace {
-        fn history_mut(&mut self) -> &mut Vec<String>;
+    /// Push the outer layer `a` if `cond` holds and `b` otherwise.
+    pub fn push_either<A, B>(
+        self,
+        cond: bool,
+        a: A,
+        b: B,
+    ) -> Layers<tower::layer::util::Stack<L, Either<A, B>>> {
+        if cond {
+            self.push_named(short_type_name::<A>(), Either::A(a))
+        } else {
+            self.push_named(short_type_name::<B>(), Either::B(b))
+        }
     }
-    struct TraceBody {
-        history: Vec<String>,
+
+    /// Push an outer layer made of a function from the inner service to the outer service.
+    pub fn push_fn<F>(self, f: F) -> Layers<tower::layer::util::Stack<L, LayerFn<F>>> {
+        self.push_named("LayerFn", layer_fn(f))
     }
-    impl Trace for TraceBody {
-        fn history_mut(&mut self) -> &mut Vec<String> {
-            &mut self.history
-        }
+}
+
+impl<L> Describe for Layers<L> {
+    fn describe(&self) -> Description {
+        self.description.clone()
     }
+}
 
-    struct TraceService<S> {
-        inner: S,
-        req_mark: String,
-        resp_mark: String,
+impl<S, L: Layer<S>> Layer<S> for Layers<L> {
+    type Service = L::Service;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        self.layers.layer(inner)
     }
-    impl<S, Req> Service<Req> for TraceService<S>
+}
+
+impl<S> Stack<S> {
+    /// Push all the layers onto the stack, in the order they were pushed onto `layers`.
+    pub fn push_layers<L>(self, layers: Layers<L>) -> Stack<L::Service>
     where
-        Req: Trace,
-        S: Service<Req, Response = Req>,
-        S::Response: 'static,
-        S::Future: 'static,
+        L: Layer<S>,
     {
-        type Response = S::Response;
-        type Error = S::Error;
-        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
-        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-            self.inner.poll_ready(cx)
-        }
-        fn call(&mut self, mut req: Req) -> Self::Future {
-            req.history_mut().push(self.req_mark.clone());
-            let fut = self.inner.call(req);
-            let resp_mark = self.resp_mark.clone();
-            let next = async move {
-                let mut resp = fut.await?;
-                resp.history_mut().push(resp_mark);
-                Ok(resp)
-            };
-            let next = Box::pin(next);
-            next
-        }
+        let (inner, description) = self.into_parts();
+        let description = description.map(|mut description| {
+            description.extend(layers.description);
+            description
+        });
+        Stack::from_parts(layers.layers.layer(inner), description)
     }
+}
 
-    struct EchoService;
-    impl<Req> Service<Req> for EchoService {
-        type Response = Req;
-        type Error = Box<Infallible>;
-        type Future = Ready<Result<Self::Response, Self::Error>>;
-        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
-        }
-        fn call(&mut self, req: Req) -> Self::Future {
-            ready(Ok(req))
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::pin};
+
+    use pipeline_test::{
+        assert_ready_ok, noop_context, EchoService, TraceBody, TraceLayer, TraceService,
+    };
+    use tower::{Service, ServiceBuilder};
+
+    use super::*;
 
     #[test]
     fn test_layers_order() {
-        struct TraceLayer1;
-        impl<S> Layer<S> for TraceLayer1 {
-            type Service = TraceService<S>;
-            fn layer(&self, inner: S) -> Self::Service {
-                let req_mark = "req_mark_1".to_string();
-                let resp_mark = "resp_mark_1".to_string();
-                TraceService {
-                    inner,
-                    req_mark,
-                    resp_mark,
-                }
-            }
-        }
+        let layers = Layers::new()
+            .push(TraceLayer::new("req_2", "resp_2"))
+            .push_named("trace_1", TraceLayer::new("req_1", "resp_1"));
+        assert_eq!(
+            layers.describe().names().collect::<Vec<_>>(),
+            ["TraceLayer", "trace_1"]
+        );
+        let mut svc = layers.layer(EchoService);
 
-        struct TraceLayer2;
-        impl<S> Layer<S> for TraceLayer2 {
-            type Service = TraceService<S>;
-            fn layer(&self, inner: S) -> Self::Service {
-                let req_mark = "req_mark_2".to_string();
-                let resp_mark = "resp_mark_2".to_string();
-                TraceService {
-                    inner,
-                    req_mark,
-                    resp_mark,
-                }
-            }
-        }
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(resp.history, ["req_1", "req_2", "resp_2", "resp_1"]);
+    }
 
-        // Build the service.
-        let layers = Layers::new().push(TraceLayer2).push(TraceLayer1);
+    #[test]
+    fn test_service_builder() {
+        let cx = &mut noop_context();
+
+        // The first layer added to a builder is the outermost.
+        let builder = ServiceBuilder::new()
+            .layer(TraceLayer::new("req_1", "resp_1"))
+            .layer(TraceLayer::new("req_2", "resp_2"));
+        let layers = Layers::from_service_builder(builder).push(TraceLayer::new("req_3", "resp_3"));
+        assert_eq!(
+            layers.describe().names().collect::<Vec<_>>(),
+            ["ServiceBuilder", "TraceLayer"]
+        );
         let mut svc = layers.layer(EchoService);
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(
+            resp.history,
+            ["req_3", "req_1", "req_2", "resp_2", "resp_1", "resp_3"]
+        );
+
+        // The layers added to the builder afterwards are the innermost.
+        let builder = Layers::new()
+            .push(TraceLayer::new("req_2", "resp_2"))
+            .push(TraceLayer::new("req_1", "resp_1"))
+            .into_service_builder()
+            .layer(TraceLayer::new("req_0", "resp_0"));
+        let mut svc = builder.service(EchoService);
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(
+            resp.history,
+            ["req_1", "req_2", "req_0", "resp_0", "resp_2", "resp_1"]
+        );
+    }
+
+    #[test]
+    fn test_push_layers() {
+        let layers = Layers::new()
+            .push_option(Some(TraceLayer::new("req_1", "resp_1")))
+            .push_option(None::<TraceLayer>)
+            .push_either(
+                false,
+                TraceLayer::new("req_a", "resp_a"),
+                TraceLayer::new("req_b", "resp_b"),
+            )
+            .push_fn(|inner| TraceService::new(inner, "req_fn", "resp_fn"));
+        let stack = Stack::described(EchoService).push_layers(layers);
+        assert_eq!(
+            stack.describe().names().collect::<Vec<_>>(),
+            ["EchoService", "TraceLayer", "TraceLayer", "LayerFn"]
+        );
+        let mut svc = stack.into_inner();
 
-        // Build the request.
-        let req = TraceBody {
-            history: Vec::new(),
-        };
-
-        // Poll the service.
-        let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-        let poll_ready =
-            <TraceService<TraceService<EchoService>> as Service<TraceBody>>::poll_ready(
-                &mut svc, cx,
-            );
-        let Poll::Ready(Ok(())) = poll_ready else {
-            panic!("poll_ready failed");
-        };
-
-        // Call the service.
-        let fut = svc.call(req);
-        pin_mut!(fut);
-        let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-        let Poll::Ready(Ok(resp)) = fut.as_mut().poll(cx) else {
-            panic!("call failed");
-        };
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
         assert_eq!(
             resp.history,
-            vec!["req_mark_1", "req_mark_2", "resp_mark_2", "resp_mark_1"]
+            ["req_fn", "req_b", "req_1", "resp_1", "resp_b", "resp_fn"]
         );
     }
 }
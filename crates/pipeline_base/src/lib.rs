@@ -1,5 +1,50 @@
+mod cache;
+mod check;
+mod clock;
+mod describe;
+mod dyn_layers;
+mod either;
+mod error;
+#[cfg(feature = "http")]
+mod extension;
+#[cfg(feature = "tokio")]
+mod failfast;
+mod filter;
+#[cfg(feature = "tokio")]
+mod gate;
+mod insert_target;
 mod layers;
+mod map_err;
+mod map_target;
+mod param;
+mod response;
 mod stack;
 
+pub use cache::Cache;
+pub use check::{SendSyncStack, ServiceFor, UnpinStack};
+#[cfg(any(test, feature = "test-util"))]
+pub use clock::ManualClock;
+pub use clock::{Clock, SystemClock};
+pub use describe::{Describe, Description};
+pub use dyn_layers::{DynLayers, DynService};
+pub use either::{Either, EitherFuture, Switch};
+pub use error::{find_cause, Error};
+#[cfg(feature = "http")]
+pub use extension::InsertExtension;
+#[cfg(feature = "tokio")]
+pub use failfast::{FailFast, FailFastError, FailFastFuture, FailFastLayer};
+pub use filter::{Filter, FilterFuture, FilterLayer, Predicate};
+#[cfg(feature = "tokio")]
+pub use gate::{Gate, GateClosed, GateFuture, GateHandle, GateLayer, GateState};
+pub use insert_target::{InsertTarget, InsertTargetLayer};
 pub use layers::Layers;
+pub use map_err::{
+    MapErr, MapErrBoxed, MapErrBoxedFuture, MapErrBoxedLayer, MapErrFuture, MapErrLayer,
+};
+pub use map_target::{MapTarget, MapTargetLayer};
+pub use param::{CloneParam, ExtractParam, InsertParam, Param};
+pub use response::{
+    AndThen, AndThenFuture, AndThenLayer, MapResponse, MapResponseFuture, MapResponseLayer,
+    OnResponse, OnResponseFuture, OnResponseLayer,
+};
 pub use stack::Stack;
@@ -0,0 +1,220 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use crate::{Error, Stack};
+
+/// Converts the errors of the inner service.
+///
+/// `F`: a function from the inner error to the outer error
+#[derive(Clone, Debug)]
+pub struct MapErr<S, F> {
+    inner: S,
+    f: F,
+}
+impl<S, F> MapErr<S, F> {
+    pub fn new(inner: S, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+impl<S, F, Req, E> Service<Req> for MapErr<S, F>
+where
+    S: Service<Req>,
+    F: FnOnce(S::Error) -> E + Clone,
+{
+    type Response = S::Response;
+    type Error = E;
+    type Future = MapErrFuture<S::Future, F>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(self.f.clone())
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        MapErrFuture {
+            future: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MapErrFuture<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: Option<F>,
+    }
+}
+impl<Fut, F, T, E1, E2> Future for MapErrFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E1>>,
+    F: FnOnce(E1) -> E2,
+{
+    type Output = Result<T, E2>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let f = this.f.take().expect("polled after completion");
+        Poll::Ready(res.map_err(f))
+    }
+}
+
+/// Boxes the errors of the inner service into `Error`.
+#[derive(Clone, Debug)]
+pub struct MapErrBoxed<S> {
+    inner: S,
+}
+impl<S> MapErrBoxed<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+impl<S, Req> Service<Req> for MapErrBoxed<S>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = MapErrBoxedFuture<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        MapErrBoxedFuture {
+            future: self.inner.call(req),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MapErrBoxedFuture<F> {
+        #[pin]
+        future: F,
+    }
+}
+impl<F, T, E> Future for MapErrBoxedFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().future.poll(cx).map_err(Into::into)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MapErrLayer<F>(F);
+impl<F> MapErrLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<S, F> Layer<S> for MapErrLayer<F>
+where
+    F: Clone,
+{
+    type Service = MapErr<S, F>;
+    fn layer(&self, inner: S) -> Self::Service {
+        MapErr::new(inner, self.0.clone())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MapErrBoxedLayer;
+impl<S> Layer<S> for MapErrBoxedLayer {
+    type Service = MapErrBoxed<S>;
+    fn layer(&self, inner: S) -> Self::Service {
+        MapErrBoxed::new(inner)
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that converts the errors of the current stack.
+    ///
+    /// `f`: a function from the error of the current stack to the new error
+    pub fn push_map_err<F>(self, f: F) -> Stack<MapErr<S, F>>
+    where
+        F: Clone,
+    {
+        self.push(MapErrLayer::new(f))
+    }
+
+    /// Push an outer layer that boxes the errors of the current stack into `Error`.
+    pub fn push_map_err_boxed(self) -> Stack<MapErrBoxed<S>> {
+        self.push(MapErrBoxedLayer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        fmt,
+        future::{ready, Ready},
+        pin::pin,
+    };
+
+    use pipeline_test::{assert_ready_err, assert_ready_ok, noop_context};
+
+    use super::*;
+    use crate::find_cause;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Refused(&'static str);
+    impl fmt::Display for Refused {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "refused {}", self.0)
+        }
+    }
+    impl std::error::Error for Refused {}
+
+    /// Refuses every request.
+    #[derive(Clone, Debug)]
+    struct RefuseService;
+    impl Service<&'static str> for RefuseService {
+        type Response = Infallible;
+        type Error = Refused;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: &'static str) -> Self::Future {
+            ready(Err(Refused(req)))
+        }
+    }
+
+    #[test]
+    fn test_map_err() {
+        let stack = Stack::new(RefuseService)
+            .push_map_err(|Refused(req)| req.len())
+            .check_clone();
+        let mut service = stack.into_inner();
+
+        // Call the service.
+        let cx = &mut noop_context();
+        let error = assert_ready_err!(pin!(service.call("hello")).poll(cx));
+        assert_eq!(error, 5);
+    }
+
+    #[test]
+    fn test_map_err_boxed() {
+        let stack = Stack::new(RefuseService).push_map_err_boxed().check_clone();
+        let mut service = stack.into_inner();
+
+        // Poll the service.
+        let cx = &mut noop_context();
+        assert_ready_ok!(service.poll_ready(cx));
+
+        // Call the service.
+        let error = assert_ready_err!(pin!(service.call("hello")).poll(cx));
+        assert_eq!(find_cause::<Refused>(&*error), Some(&Refused("hello")));
+    }
+}
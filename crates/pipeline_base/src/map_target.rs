@@ -0,0 +1,96 @@
+use std::task::{Context, Poll};
+
+use tower::{Layer, Service};
+
+use crate::Stack;
+
+/// Converts the target before handing it to the inner service.
+///
+/// `F`: a function from the outer target to the inner target
+#[derive(Clone, Debug)]
+pub struct MapTarget<S, F> {
+    inner: S,
+    f: F,
+}
+impl<S, F> MapTarget<S, F> {
+    pub fn new(inner: S, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+impl<S, F, Tgt, InnerTgt> Service<Tgt> for MapTarget<S, F>
+where
+    F: FnMut(Tgt) -> InnerTgt,
+    S: Service<InnerTgt>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let target = (self.f)(target);
+        self.inner.call(target)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MapTargetLayer<F>(F);
+impl<F> MapTargetLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<S, F> Layer<S> for MapTargetLayer<F>
+where
+    F: Clone,
+{
+    type Service = MapTarget<S, F>;
+    fn layer(&self, inner: S) -> Self::Service {
+        MapTarget::new(inner, self.0.clone())
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that converts the target before it reaches the current stack.
+    ///
+    /// `f`: a function from the new outer target to the target of the current stack
+    pub fn push_map_target<F>(self, f: F) -> Stack<MapTarget<S, F>>
+    where
+        F: Clone,
+    {
+        self.push(MapTargetLayer::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, net::SocketAddr, pin::pin};
+
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+
+    use super::*;
+
+    #[test]
+    fn test_map_target() {
+        #[derive(Debug, PartialEq, Eq)]
+        struct Endpoint {
+            addr: SocketAddr,
+            tls: bool,
+        }
+
+        // Build a stack.
+        let stack = Stack::new(EchoService)
+            .push_map_target(|addr: SocketAddr| Endpoint { addr, tls: false })
+            .check_clone();
+        let mut service = stack.into_inner();
+
+        let addr: SocketAddr = "127.0.0.1:80".parse().unwrap();
+
+        // Call the service.
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<SocketAddr>::poll_ready(&mut service, cx));
+        let resp = assert_ready_ok!(pin!(service.call(addr)).poll(cx));
+        assert_eq!(resp, Endpoint { addr, tls: false });
+    }
+}
@@ -0,0 +1,104 @@
+/// A target that provides a typed parameter `P`.
+///
+/// Layers bound their target by `Param<P>` instead of a concrete target type so they can be reused across target types.
+pub trait Param<P> {
+    fn param(&self) -> P;
+}
+
+/// Every target provides an owned copy of itself.
+impl<T: ToOwned> Param<T::Owned> for T {
+    fn param(&self) -> T::Owned {
+        self.to_owned()
+    }
+}
+
+/// Extracts a parameter `P` from a target `T`.
+pub trait ExtractParam<P, T> {
+    fn extract_param(&self, target: &T) -> P;
+}
+
+/// Extracts the parameter the target provides.
+impl<P, T: Param<P>> ExtractParam<P, T> for () {
+    fn extract_param(&self, target: &T) -> P {
+        target.param()
+    }
+}
+
+/// Inserts a parameter `P` into a target `T`, producing a new target.
+pub trait InsertParam<P, T> {
+    type Target;
+
+    fn insert_param(&self, param: P, target: T) -> Self::Target;
+}
+
+/// Pairs the parameter with the target.
+impl<P, T> InsertParam<P, T> for () {
+    type Target = (P, T);
+    fn insert_param(&self, param: P, target: T) -> Self::Target {
+        (param, target)
+    }
+}
+
+/// Extracts a fixed parameter regardless of the target.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CloneParam<P>(P);
+impl<P> CloneParam<P> {
+    pub fn new(param: P) -> Self {
+        Self(param)
+    }
+}
+impl<P> From<P> for CloneParam<P> {
+    fn from(param: P) -> Self {
+        Self(param)
+    }
+}
+impl<P: Clone, T> ExtractParam<P, T> for CloneParam<P> {
+    fn extract_param(&self, _: &T) -> P {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Timeout(Duration);
+
+    struct Endpoint {
+        timeout: Duration,
+    }
+    impl Param<Timeout> for Endpoint {
+        fn param(&self) -> Timeout {
+            Timeout(self.timeout)
+        }
+    }
+
+    fn extract<P, T, X: ExtractParam<P, T>>(extract: &X, target: &T) -> P {
+        extract.extract_param(target)
+    }
+
+    #[test]
+    fn test_extract_param() {
+        let endpoint = Endpoint {
+            timeout: Duration::from_secs(1),
+        };
+        let timeout: Timeout = extract(&(), &endpoint);
+        assert_eq!(timeout, Timeout(Duration::from_secs(1)));
+
+        let timeout: Timeout =
+            extract(&CloneParam::new(Timeout(Duration::from_secs(2))), &endpoint);
+        assert_eq!(timeout, Timeout(Duration::from_secs(2)));
+
+        let target: String = extract(&(), &"target".to_string());
+        assert_eq!(target, "target");
+    }
+
+    #[test]
+    fn test_insert_param() {
+        let target = ().insert_param(Timeout(Duration::from_secs(1)), "target");
+        assert_eq!(target, (Timeout(Duration::from_secs(1)), "target"));
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+use crate::Stack;
+
+/// Converts the responses of the inner service.
+///
+/// `F`: a function from the inner response to the outer response
+#[derive(Clone, Debug)]
+pub struct MapResponse<S, F> {
+    inner: S,
+    f: F,
+}
+impl<S, F> MapResponse<S, F> {
+    pub fn new(inner: S, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+impl<S, F, Req, R> Service<Req> for MapResponse<S, F>
+where
+    S: Service<Req>,
+    F: FnOnce(S::Response) -> R + Clone,
+{
+    type Response = R;
+    type Error = S::Error;
+    type Future = MapResponseFuture<S::Future, F>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        MapResponseFuture {
+            future: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MapResponseFuture<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: Option<F>,
+    }
+}
+impl<Fut, F, T, E, R> Future for MapResponseFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E>>,
+    F: FnOnce(T) -> R,
+{
+    type Output = Result<R, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let f = this.f.take().expect("polled after completion");
+        Poll::Ready(res.map(f))
+    }
+}
+
+/// Converts the responses of the inner service asynchronously.
+///
+/// `F`: a function from the inner response to a future of the outer response
+#[derive(Clone, Debug)]
+pub struct AndThen<S, F> {
+    inner: S,
+    f: F,
+}
+impl<S, F> AndThen<S, F> {
+    pub fn new(inner: S, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+impl<S, F, Req, Fut, R> Service<Req> for AndThen<S, F>
+where
+    S: Service<Req>,
+    F: FnOnce(S::Response) -> Fut + Clone,
+    Fut: Future<Output = Result<R, S::Error>>,
+{
+    type Response = R;
+    type Error = S::Error;
+    type Future = AndThenFuture<S::Future, F, Fut>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        AndThenFuture::First {
+            future: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    #[project = AndThenProj]
+    pub enum AndThenFuture<Fut1, F, Fut2> {
+        First {
+            #[pin]
+            future: Fut1,
+            f: Option<F>,
+        },
+        Second {
+            #[pin]
+            future: Fut2,
+        },
+    }
+}
+impl<Fut1, F, Fut2, T, E, R> Future for AndThenFuture<Fut1, F, Fut2>
+where
+    Fut1: Future<Output = Result<T, E>>,
+    F: FnOnce(T) -> Fut2,
+    Fut2: Future<Output = Result<R, E>>,
+{
+    type Output = Result<R, E>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.as_mut().project() {
+                AndThenProj::First { future, f } => {
+                    let resp = match future.poll(cx) {
+                        Poll::Ready(Ok(resp)) => resp,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Pending => return Poll::Pending,
+                    };
+                    let f = f.take().expect("polled after completion");
+                    let future = f(resp);
+                    self.set(AndThenFuture::Second { future });
+                }
+                AndThenProj::Second { future } => return future.poll(cx),
+            }
+        }
+    }
+}
+
+/// Inspects or modifies the responses of the inner service in place.
+///
+/// `F`: a function called with every successful response
+#[derive(Clone, Debug)]
+pub struct OnResponse<S, F> {
+    inner: S,
+    f: F,
+}
+impl<S, F> OnResponse<S, F> {
+    pub fn new(inner: S, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+impl<S, F, Req> Service<Req> for OnResponse<S, F>
+where
+    S: Service<Req>,
+    F: FnOnce(&mut S::Response) + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = OnResponseFuture<S::Future, F>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        OnResponseFuture {
+            future: self.inner.call(req),
+            f: Some(self.f.clone()),
+        }
+    }
+}
+
+pin_project! {
+    pub struct OnResponseFuture<Fut, F> {
+        #[pin]
+        future: Fut,
+        f: Option<F>,
+    }
+}
+impl<Fut, F, T, E> Future for OnResponseFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E>>,
+    F: FnOnce(&mut T),
+{
+    type Output = Result<T, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut resp = match this.future.poll(cx) {
+            Poll::Ready(Ok(resp)) => resp,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        let f = this.f.take().expect("polled after completion");
+        f(&mut resp);
+        Poll::Ready(Ok(resp))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MapResponseLayer<F>(F);
+impl<F> MapResponseLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<S, F> Layer<S> for MapResponseLayer<F>
+where
+    F: Clone,
+{
+    type Service = MapResponse<S, F>;
+    fn layer(&self, inner: S) -> Self::Service {
+        MapResponse::new(inner, self.0.clone())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AndThenLayer<F>(F);
+impl<F> AndThenLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<S, F> Layer<S> for AndThenLayer<F>
+where
+    F: Clone,
+{
+    type Service = AndThen<S, F>;
+    fn layer(&self, inner: S) -> Self::Service {
+        AndThen::new(inner, self.0.clone())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OnResponseLayer<F>(F);
+impl<F> OnResponseLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<S, F> Layer<S> for OnResponseLayer<F>
+where
+    F: Clone,
+{
+    type Service = OnResponse<S, F>;
+    fn layer(&self, inner: S) -> Self::Service {
+        OnResponse::new(inner, self.0.clone())
+    }
+}
+
+impl<S> Stack<S> {
+    /// Push an outer layer that converts the responses of the current stack.
+    ///
+    /// `f`: a function from the response of the current stack to the new response
+    pub fn push_map_response<F>(self, f: F) -> Stack<MapResponse<S, F>>
+    where
+        F: Clone,
+    {
+        self.push(MapResponseLayer::new(f))
+    }
+
+    /// Push an outer layer that converts the responses of the current stack asynchronously.
+    ///
+    /// `f`: a function from the response of the current stack to a future of the new response
+    pub fn push_and_then<F>(self, f: F) -> Stack<AndThen<S, F>>
+    where
+        F: Clone,
+    {
+        self.push(AndThenLayer::new(f))
+    }
+
+    /// Push an outer layer that inspects or modifies the responses of the current stack.
+    ///
+    /// `f`: a function called with every successful response
+    pub fn push_on_response<F>(self, f: F) -> Stack<OnResponse<S, F>>
+    where
+        F: Clone,
+    {
+        self.push(OnResponseLayer::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, pin::pin};
+
+    use pipeline_test::{assert_ready_err, assert_ready_ok, noop_context, EchoService};
+
+    use super::*;
+
+    #[test]
+    fn test_response() {
+        let stack = Stack::new(EchoService)
+            .push_map_err(|never: Infallible| match never {})
+            .push_map_response(|resp: String| resp.len())
+            .push_and_then(|len: usize| async move {
+                if len > 3 {
+                    Ok(len * 2)
+                } else {
+                    Err(())
+                }
+            })
+            .push_on_response(|resp: &mut usize| *resp += 1)
+            .check_clone();
+        let mut service = stack.into_inner();
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<String>::poll_ready(&mut service, cx));
+        let resp = assert_ready_ok!(pin!(service.call("hello".to_string())).poll(cx));
+        assert_eq!(resp, 11);
+        assert_ready_err!(pin!(service.call("hi".to_string())).poll(cx));
+    }
+}
@@ -1,24 +1,76 @@
+use std::borrow::Cow;
+
 use tower::Layer;
 
+use crate::{
+    describe::{short_type_name, Description},
+    Describe, SendSyncStack, ServiceFor, UnpinStack,
+};
+
+/// `S`: the service at the top of the stack
+///
+/// A stack created with `Stack::described` also records the names of its layers so it can describe itself at runtime.
+/// Other stacks record nothing and describe themselves by the type of the inner service.
 #[derive(Clone, Debug)]
-pub struct Stack<S>(S);
+pub struct Stack<S> {
+    inner: S,
+    description: Option<Description>,
+}
 impl<S> Stack<S> {
     pub fn new(inner: S) -> Self {
-        Self(inner)
+        Self::from_parts(inner, None)
+    }
+
+    /// Create a stack that records the names of the layers pushed onto it.
+    pub fn described(inner: S) -> Self {
+        Self::from_parts(inner, Some(Description::of::<S>()))
+    }
+
+    /// Create a stack from a service and the description of its composition, if it is recorded.
+    pub fn from_parts(inner: S, description: Option<Description>) -> Self {
+        Self { inner, description }
     }
 
     /// Get the inner service.
     pub fn into_inner(self) -> S {
-        self.0
+        self.inner
+    }
+
+    /// Get the inner service and the description of its composition, if it is recorded.
+    pub fn into_parts(self) -> (S, Option<Description>) {
+        (self.inner, self.description)
     }
 
     /// Push an outer layer onto the stack.
+    ///
+    /// The layer is described by its type name.
     pub fn push<L>(self, layer: L) -> Stack<L::Service>
     where
         L: Layer<S>,
     {
-        let service = layer.layer(self.0);
-        Stack(service)
+        self.push_with(short_type_name::<L>, layer)
+    }
+
+    /// Push an outer layer onto the stack and describe it by `name`.
+    pub fn push_named<L>(self, name: impl Into<Cow<'static, str>>, layer: L) -> Stack<L::Service>
+    where
+        L: Layer<S>,
+    {
+        self.push_with(|| name, layer)
+    }
+
+    /// Push an outer layer, naming it only if the description is recorded.
+    fn push_with<L, N>(self, name: impl FnOnce() -> N, layer: L) -> Stack<L::Service>
+    where
+        L: Layer<S>,
+        N: Into<Cow<'static, str>>,
+    {
+        let description = self.description.map(|mut description| {
+            description.push(name());
+            description
+        });
+        let service = layer.layer(self.inner);
+        Stack::from_parts(service, description)
     }
 
     /// To restrict the type of the inner service, we can add a bound to the type parameter `S`.
@@ -28,36 +80,55 @@ impl<S> Stack<S> {
     {
         self
     }
+
+    /// Make sure the inner service is a `Service` of `Req`.
+    pub fn check_service<Req>(self) -> Stack<S>
+    where
+        S: ServiceFor<Req>,
+    {
+        self
+    }
+
+    /// Make sure the inner service can be sent to and shared with other threads.
+    pub fn check_send_sync(self) -> Stack<S>
+    where
+        S: SendSyncStack,
+    {
+        self
+    }
+
+    /// Make sure the inner service is `Unpin`.
+    pub fn check_unpin(self) -> Stack<S>
+    where
+        S: UnpinStack,
+    {
+        self
+    }
+}
+impl<S> Describe for Stack<S> {
+    fn describe(&self) -> Description {
+        self.description
+            .clone()
+            .unwrap_or_else(Description::of::<S>)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::{
-        future::{ready, Ready},
+        convert::Infallible,
+        future::{ready, Future, Ready},
+        pin::pin,
         task::{Context, Poll},
     };
 
-    use futures::{pin_mut, Future};
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
     use tower::Service;
 
     use super::*;
 
     #[test]
     fn test_stack_echo() {
-        #[derive(Clone, Debug)]
-        struct EchoService;
-        impl<Req> Service<Req> for EchoService {
-            type Response = Req;
-            type Error = ();
-            type Future = Ready<Result<Self::Response, Self::Error>>;
-            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-                Poll::Ready(Ok(()))
-            }
-            fn call(&mut self, req: Req) -> Self::Future {
-                ready(Ok(req))
-            }
-        }
-
         struct EmptyLayer;
         impl<S> Layer<S> for EmptyLayer {
             type Service = S;
@@ -67,112 +138,92 @@ mod tests {
         }
 
         // Build a stack of layers.
-        let stack = Stack::new(EchoService {}).push(EmptyLayer {}).check_clone();
+        let stack = Stack::new(EchoService)
+            .push(EmptyLayer)
+            .check_clone()
+            .check_service::<&str>()
+            .check_send_sync()
+            .check_unpin();
         let mut service: EchoService = stack.into_inner();
 
         // Use the service.
-        {
-            let req = "hello";
-
-            // Poll the service.
-            let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-            assert_eq!(
-                <EchoService as Service<&str>>::poll_ready(&mut service, cx),
-                Poll::Ready(Ok(()))
-            );
-
-            // Call the service.
-            let fut = service.call(req);
-            pin_mut!(fut);
-            let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-            let resp = fut.as_mut().poll(cx);
-            assert_eq!(resp, Poll::Ready(Ok(req)));
-        }
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<&str>::poll_ready(&mut service, cx));
+        let resp = assert_ready_ok!(pin!(service.call("hello")).poll(cx));
+        assert_eq!(resp, "hello");
     }
 
     #[test]
-    fn test_stack_switch() {
-        #[derive(Clone, Debug)]
-        struct PrintService;
-        impl Service<String> for PrintService {
-            type Response = ();
-            type Error = ();
-            type Future = Ready<Result<Self::Response, Self::Error>>;
-            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-                Poll::Ready(Ok(()))
-            }
-            fn call(&mut self, req: String) -> Self::Future {
-                println!("{}", req);
-                ready(Ok(()))
+    fn test_describe() {
+        struct TraceLayer<T>(T);
+        impl<S, T> Layer<S> for TraceLayer<T> {
+            type Service = S;
+            fn layer(&self, inner: S) -> Self::Service {
+                inner
             }
         }
 
+        let stack = Stack::described(EchoService)
+            .push(TraceLayer(1u8))
+            .push_named("trace", TraceLayer(2u8));
+        let description = stack.describe();
+        assert_eq!(
+            description.names().collect::<Vec<_>>(),
+            ["EchoService", "TraceLayer<u8>", "trace"]
+        );
+        assert_eq!(
+            description.to_string(),
+            "EchoService\nTraceLayer<u8>\ntrace\n"
+        );
+
+        // Without recording, the stack is described by the type of the inner service.
+        let stack = Stack::new(EchoService).push(TraceLayer(1u8));
+        assert_eq!(
+            stack.describe().names().collect::<Vec<_>>(),
+            ["EchoService"]
+        );
+    }
+
+    #[test]
+    fn test_stack_switch() {
         enum Request {
-            Print(String),
+            Echo(String),
             Discard,
         }
 
         #[derive(Clone, Debug)]
         struct SwitchService {
-            print: PrintService,
+            echo: EchoService,
         }
         impl Service<Request> for SwitchService {
-            type Response = ();
-            type Error = ();
+            type Response = String;
+            type Error = Infallible;
             type Future = Ready<Result<Self::Response, Self::Error>>;
             fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
                 Poll::Ready(Ok(()))
             }
             fn call(&mut self, req: Request) -> Self::Future {
                 match req {
-                    Request::Print(req) => self.print.call(req),
-                    Request::Discard => ready(Ok(())),
+                    Request::Echo(req) => self.echo.call(req),
+                    Request::Discard => ready(Ok(String::new())),
                 }
             }
         }
 
         // Build a stack.
         let mut service = Stack::new(SwitchService {
-            print: Stack::new(PrintService {}).into_inner(),
+            echo: Stack::new(EchoService).into_inner(),
         })
+        .check_service::<Request>()
         .into_inner();
 
         // Use the service.
-        {
-            let req = Request::Print("hello".to_string());
-
-            // Poll the service.
-            let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-            assert_eq!(
-                <SwitchService as Service<Request>>::poll_ready(&mut service, cx),
-                Poll::Ready(Ok(()))
-            );
-
-            // Call the service.
-            let fut = service.call(req);
-            pin_mut!(fut);
-            let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-            let resp = fut.as_mut().poll(cx);
-            assert_eq!(resp, Poll::Ready(Ok(())));
-        }
-
-        // Use the service.
-        {
-            let req = Request::Discard;
-
-            // Poll the service.
-            let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-            assert_eq!(
-                <SwitchService as Service<Request>>::poll_ready(&mut service, cx),
-                Poll::Ready(Ok(()))
-            );
-
-            // Call the service.
-            let fut = service.call(req);
-            pin_mut!(fut);
-            let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-            let resp = fut.as_mut().poll(cx);
-            assert_eq!(resp, Poll::Ready(Ok(())));
+        let cx = &mut noop_context();
+        let reqs = [Request::Echo("hello".to_string()), Request::Discard];
+        for (req, expected) in reqs.into_iter().zip(["hello", ""]) {
+            assert_ready_ok!(service.poll_ready(cx));
+            let resp = assert_ready_ok!(pin!(service.call(req)).poll(cx));
+            assert_eq!(resp, expected);
         }
     }
 }
@@ -0,0 +1,6 @@
+/// The `check_*` helpers of `Stack` reject stacks that do not fit.
+#[test]
+fn test_compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,8 @@
+use pipeline_base::Stack;
+use pipeline_test::EchoService;
+
+struct NotClone<S>(S);
+
+fn main() {
+    Stack::new(NotClone(EchoService)).check_clone();
+}
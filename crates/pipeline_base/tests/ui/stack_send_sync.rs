@@ -0,0 +1,8 @@
+use std::rc::Rc;
+
+use pipeline_base::Stack;
+use pipeline_test::EchoService;
+
+fn main() {
+    Stack::new(Rc::new(EchoService)).check_send_sync();
+}
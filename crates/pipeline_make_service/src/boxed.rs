@@ -0,0 +1,292 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tower::{
+    layer::{layer_fn, LayerFn},
+    util::{BoxService, UnsyncBoxService},
+    Service, ServiceExt,
+};
+
+use crate::{MakeStack, OnService};
+
+/// A boxed `Future + Send` trait object.
+type BoxFuture<T, E> = Pin<Box<dyn Future<Output = Result<T, E>> + Send>>;
+
+/// A type-erased `Send` make service whose made services are also type-erased.
+///
+/// The make error and the error of the made services must be the same type.
+pub struct BoxMakeService<Tgt, Req, Resp, Err>(BoxService<Tgt, BoxService<Req, Resp, Err>, Err>);
+impl<Tgt, Req, Resp, Err> BoxMakeService<Tgt, Req, Resp, Err> {
+    pub fn new<M>(inner: M) -> Self
+    where
+        M: Service<Tgt, Error = Err> + Send + 'static,
+        M::Future: Send + 'static,
+        M::Response: Service<Req, Response = Resp, Error = Err> + Send + 'static,
+        <M::Response as Service<Req>>::Future: Send + 'static,
+        Req: 'static,
+        Resp: 'static,
+        Err: 'static,
+    {
+        let inner = inner.map_response(BoxService::new);
+        Self(BoxService::new(inner))
+    }
+}
+impl<Tgt, Req, Resp, Err> Service<Tgt> for BoxMakeService<Tgt, Req, Resp, Err> {
+    type Response = BoxService<Req, Resp, Err>;
+    type Error = Err;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        self.0.call(target)
+    }
+}
+impl<Tgt, Req, Resp, Err> fmt::Debug for BoxMakeService<Tgt, Req, Resp, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxMakeService").finish()
+    }
+}
+
+/// The same as `BoxMakeService` but neither the make service nor the made services need to be `Send`.
+pub struct UnsyncBoxMakeService<Tgt, Req, Resp, Err>(
+    UnsyncBoxService<Tgt, UnsyncBoxService<Req, Resp, Err>, Err>,
+);
+impl<Tgt, Req, Resp, Err> UnsyncBoxMakeService<Tgt, Req, Resp, Err> {
+    pub fn new<M>(inner: M) -> Self
+    where
+        M: Service<Tgt, Error = Err> + 'static,
+        M::Future: 'static,
+        M::Response: Service<Req, Response = Resp, Error = Err> + 'static,
+        <M::Response as Service<Req>>::Future: 'static,
+        Req: 'static,
+        Resp: 'static,
+        Err: 'static,
+    {
+        let inner = inner.map_response(UnsyncBoxService::new);
+        Self(UnsyncBoxService::new(inner))
+    }
+}
+impl<Tgt, Req, Resp, Err> Service<Tgt> for UnsyncBoxMakeService<Tgt, Req, Resp, Err> {
+    type Response = UnsyncBoxService<Req, Resp, Err>;
+    type Error = Err;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        self.0.call(target)
+    }
+}
+impl<Tgt, Req, Resp, Err> fmt::Debug for UnsyncBoxMakeService<Tgt, Req, Resp, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnsyncBoxMakeService").finish()
+    }
+}
+
+/// The response type of the services made by `M` for `Req`.
+type MadeResponse<M, Tgt, Req> = <<M as Service<Tgt>>::Response as Service<Req>>::Response;
+
+impl<M> MakeStack<M> {
+    /// Erase the type of the make service and of the services it makes.
+    ///
+    /// `Tgt`: the target type of the stack
+    ///
+    /// `Req`: the request type of the stack
+    #[allow(clippy::type_complexity)]
+    pub fn push_box_make<Tgt, Req>(
+        self,
+    ) -> MakeStack<BoxMakeService<Tgt, Req, MadeResponse<M, Tgt, Req>, M::Error>>
+    where
+        M: Service<Tgt> + Send + 'static,
+        M::Future: Send + 'static,
+        M::Response: Service<Req, Error = M::Error> + Send + 'static,
+        <M::Response as Service<Req>>::Future: Send + 'static,
+        MadeResponse<M, Tgt, Req>: 'static,
+        M::Error: 'static,
+        Req: 'static,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("BoxMakeService", layer_fn(BoxMakeService::new));
+        MakeStack::new::<Tgt>(stack).check_make::<Tgt, Req>()
+    }
+
+    /// The same as `push_box_make` but nothing needs to be `Send`.
+    #[allow(clippy::type_complexity)]
+    pub fn push_unsync_box_make<Tgt, Req>(
+        self,
+    ) -> MakeStack<UnsyncBoxMakeService<Tgt, Req, MadeResponse<M, Tgt, Req>, M::Error>>
+    where
+        M: Service<Tgt> + 'static,
+        M::Future: 'static,
+        M::Response: Service<Req, Error = M::Error> + 'static,
+        <M::Response as Service<Req>>::Future: 'static,
+        MadeResponse<M, Tgt, Req>: 'static,
+        M::Error: 'static,
+        Req: 'static,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("UnsyncBoxMakeService", layer_fn(UnsyncBoxMakeService::new));
+        MakeStack::new::<Tgt>(stack).check_make::<Tgt, Req>()
+    }
+
+    /// Erase the type of the made services but keep the type of the make service.
+    ///
+    /// `Tgt`: the target type of the stack
+    ///
+    /// `Req`: the request type of the stack
+    #[allow(clippy::type_complexity)]
+    pub fn push_on_service_box<Tgt, Req>(
+        self,
+    ) -> MakeStack<
+        OnService<
+            LayerFn<
+                fn(
+                    M::Response,
+                ) -> BoxService<
+                    Req,
+                    MadeResponse<M, Tgt, Req>,
+                    <M::Response as Service<Req>>::Error,
+                >,
+            >,
+            M,
+        >,
+    >
+    where
+        M: Service<Tgt>,
+        M::Response: Service<Req> + Send + 'static,
+        <M::Response as Service<Req>>::Future: Send + 'static,
+        MadeResponse<M, Tgt, Req>: 'static,
+        <M::Response as Service<Req>>::Error: 'static,
+        Req: 'static,
+    {
+        self.push_on_service::<Tgt, Req, _>(BoxService::layer())
+    }
+
+    /// The same as `push_on_service_box` but the made services need not be `Send`.
+    #[allow(clippy::type_complexity)]
+    pub fn push_on_service_unsync_box<Tgt, Req>(
+        self,
+    ) -> MakeStack<
+        OnService<
+            LayerFn<
+                fn(
+                    M::Response,
+                ) -> UnsyncBoxService<
+                    Req,
+                    MadeResponse<M, Tgt, Req>,
+                    <M::Response as Service<Req>>::Error,
+                >,
+            >,
+            M,
+        >,
+    >
+    where
+        M: Service<Tgt>,
+        M::Response: Service<Req> + 'static,
+        <M::Response as Service<Req>>::Future: 'static,
+        MadeResponse<M, Tgt, Req>: 'static,
+        <M::Response as Service<Req>>::Error: 'static,
+        Req: 'static,
+    {
+        self.push_on_service::<Tgt, Req, _>(UnsyncBoxService::layer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+        pin::pin,
+        rc::Rc,
+    };
+
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+    use tower::MakeService;
+
+    use super::*;
+
+    struct MakeEcho;
+    impl<Tgt> Service<Tgt> for MakeEcho {
+        type Response = EchoService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: Tgt) -> Self::Future {
+            ready(Ok(EchoService))
+        }
+    }
+
+    fn make_and_call<M, Tgt>(make_svc: &mut M, target: Tgt, req: String) -> String
+    where
+        M: MakeService<Tgt, String, Response = String>,
+        M::MakeError: fmt::Debug,
+        M::Error: fmt::Debug,
+    {
+        let cx = &mut noop_context();
+        let mut svc = assert_ready_ok!(pin!(make_svc.make_service(target)).poll(cx));
+        assert_ready_ok!(pin!(svc.call(req)).poll(cx))
+    }
+
+    #[test]
+    fn test_box_make() {
+        /// The stack type can be named in a struct field.
+        struct Proxy {
+            make_svc: BoxMakeService<u16, String, String, Infallible>,
+        }
+
+        let make_stack = MakeStack::new::<u16>(Stack::new(MakeEcho)).push_box_make::<u16, String>();
+        let mut proxy = Proxy {
+            make_svc: make_stack.into_inner().into_inner(),
+        };
+
+        let resp = make_and_call(&mut proxy.make_svc, 80, "hello".to_string());
+        assert_eq!(resp, "hello");
+    }
+
+    #[test]
+    fn test_unsync_box_make() {
+        let make_stack = MakeStack::new::<Rc<str>>(Stack::new(MakeEcho))
+            .push_unsync_box_make::<Rc<str>, String>();
+        let mut make_svc: UnsyncBoxMakeService<Rc<str>, String, String, Infallible> =
+            make_stack.into_inner().into_inner();
+
+        let resp = make_and_call(&mut make_svc, "localhost".into(), "hello".to_string());
+        assert_eq!(resp, "hello");
+    }
+
+    #[test]
+    fn test_on_service_box() {
+        let make_stack =
+            MakeStack::new::<u16>(Stack::new(MakeEcho)).push_on_service_box::<u16, String>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        // The made services are boxed.
+        let cx = &mut noop_context();
+        let svc = assert_ready_ok!(pin!(make_svc.call(80)).poll(cx));
+        let _: BoxService<String, String, Infallible> = svc;
+
+        let resp = make_and_call(&mut make_svc, 80, "hello".to_string());
+        assert_eq!(resp, "hello");
+
+        let make_stack = MakeStack::new::<Rc<str>>(Stack::new(MakeEcho))
+            .push_on_service_unsync_box::<Rc<str>, String>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+        let resp = make_and_call(
+            &mut make_svc,
+            Rc::<str>::from("localhost"),
+            "hello".to_string(),
+        );
+        assert_eq!(resp, "hello");
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{Cache, Clock, Error, SystemClock};
+use tokio::sync::oneshot;
+use tower::{Layer, Service};
+
+use crate::{MakeError, MakeServiceFor, MakeStack};
+
+/// Makes at most one service per target and hands out clones of it.
+///
+/// Calls for a target that is still being made wait for that make instead of starting another one. If the make fails, every waiting call fails with the same `MakeError`.
+///
+/// A cached service is evicted after not being handed out for the idle timeout.
+///
+/// `M`: a thing that makes services
+///
+/// `S`: the service made by `M`
+pub struct MakeCache<M, Tgt, S, C = SystemClock> {
+    inner: M,
+    shared: Arc<Mutex<Shared<Tgt, S, C>>>,
+}
+struct Shared<Tgt, S, C> {
+    cache: Cache<Tgt, S, C>,
+    /// The calls waiting on each target that is being made.
+    making: HashMap<Tgt, Vec<oneshot::Sender<Result<S, MakeError>>>>,
+}
+impl<M, Tgt, S, C> MakeCache<M, Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock,
+{
+    pub fn new(inner: M, idle_timeout: Duration, clock: C) -> Self {
+        let shared = Shared {
+            cache: Cache::new(idle_timeout, clock),
+            making: HashMap::new(),
+        };
+        Self {
+            inner,
+            shared: Arc::new(Mutex::new(shared)),
+        }
+    }
+}
+/// Clones share the same cache.
+impl<M: Clone, Tgt, S, C> Clone for MakeCache<M, Tgt, S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+impl<M, Tgt, C> Service<Tgt> for MakeCache<M, Tgt, M::Response, C>
+where
+    Tgt: Hash + Eq + Clone,
+    M: Service<Tgt>,
+    M::Error: Into<Error>,
+    M::Response: Clone,
+    C: Clock,
+{
+    type Response = M::Response;
+    type Error = MakeError;
+    type Future = MakeCacheFuture<M::Future, Tgt, M::Response, C>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner
+            .poll_ready(cx)
+            .map_err(|e| MakeError::new(e.into()))
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(service) = shared.cache.get(&target) {
+            return MakeCacheFuture::Cached {
+                service: Some(service),
+            };
+        }
+        if let Some(waiters) = shared.making.get_mut(&target) {
+            let (tx, rx) = oneshot::channel();
+            waiters.push(tx);
+            return MakeCacheFuture::Waiting { rx };
+        }
+        shared.making.insert(target.clone(), Vec::new());
+        drop(shared);
+        MakeCacheFuture::Making {
+            future: self.inner.call(target.clone()),
+            in_flight: InFlight {
+                target: Some(target),
+                shared: self.shared.clone(),
+            },
+        }
+    }
+}
+
+/// Hands the result of a make to the calls waiting on it.
+///
+/// Dropping it before the make finishes fails the waiting calls.
+struct InFlight<Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock,
+{
+    target: Option<Tgt>,
+    shared: Arc<Mutex<Shared<Tgt, S, C>>>,
+}
+impl<Tgt, S, C> InFlight<Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock,
+{
+    fn finish(&mut self, result: Result<S, MakeError>) {
+        let Some(target) = self.target.take() else {
+            return;
+        };
+        let mut shared = self.shared.lock().unwrap();
+        let waiters = shared.making.remove(&target).unwrap_or_default();
+        if let Ok(service) = &result {
+            shared.cache.insert(target, service.clone());
+        }
+        drop(shared);
+        for waiter in waiters {
+            // The waiting call may have been dropped.
+            let _ = waiter.send(result.clone());
+        }
+    }
+}
+impl<Tgt, S, C> Drop for InFlight<Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock,
+{
+    fn drop(&mut self) {
+        self.finish(Err(canceled()));
+    }
+}
+
+fn canceled() -> MakeError {
+    MakeError::new("make was dropped before it finished".into())
+}
+
+pin_project! {
+    #[project = MakeCacheFutureProj]
+    pub enum MakeCacheFuture<F, Tgt, S, C>
+    where
+        Tgt: Hash,
+        Tgt: Eq,
+        S: Clone,
+        C: Clock,
+    {
+        Cached { service: Option<S> },
+        Waiting { rx: oneshot::Receiver<Result<S, MakeError>> },
+        Making {
+            #[pin]
+            future: F,
+            in_flight: InFlight<Tgt, S, C>,
+        },
+    }
+}
+impl<F, Tgt, S, C, E> Future for MakeCacheFuture<F, Tgt, S, C>
+where
+    F: Future<Output = Result<S, E>>,
+    E: Into<Error>,
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock,
+{
+    type Output = Result<S, MakeError>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            MakeCacheFutureProj::Cached { service } => {
+                let service = service.take().expect("polled after completion");
+                Poll::Ready(Ok(service))
+            }
+            MakeCacheFutureProj::Waiting { rx } => match Pin::new(rx).poll(cx) {
+                Poll::Ready(Ok(result)) => Poll::Ready(result),
+                // The make was dropped without finishing, e.g. during a panic.
+                Poll::Ready(Err(_)) => Poll::Ready(Err(canceled())),
+                Poll::Pending => Poll::Pending,
+            },
+            MakeCacheFutureProj::Making { future, in_flight } => {
+                let result = match future.poll(cx) {
+                    Poll::Ready(result) => result.map_err(|e| MakeError::new(e.into())),
+                    Poll::Pending => return Poll::Pending,
+                };
+                assert!(in_flight.target.is_some(), "polled after completion");
+                in_flight.finish(result.clone());
+                Poll::Ready(result)
+            }
+        }
+    }
+}
+
+pub struct MakeCacheLayer<Tgt, S, C = SystemClock> {
+    idle_timeout: Duration,
+    clock: C,
+    _cache: PhantomData<fn(Tgt) -> S>,
+}
+impl<Tgt, S, C> MakeCacheLayer<Tgt, S, C> {
+    pub fn new(idle_timeout: Duration, clock: C) -> Self {
+        Self {
+            idle_timeout,
+            clock,
+            _cache: PhantomData,
+        }
+    }
+}
+impl<Tgt, S, C: Clone> Clone for MakeCacheLayer<Tgt, S, C> {
+    fn clone(&self) -> Self {
+        Self::new(self.idle_timeout, self.clock.clone())
+    }
+}
+/// Every layered service gets its own cache.
+impl<M, Tgt, S, C> Layer<M> for MakeCacheLayer<Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock + Clone,
+{
+    type Service = MakeCache<M, Tgt, S, C>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeCache::new(inner, self.idle_timeout, self.clock.clone())
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Make at most one service per target and hand out clones of it.
+    ///
+    /// A cached service is evicted after not being handed out for `idle_timeout`.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_cache<Tgt, Req>(
+        self,
+        idle_timeout: Duration,
+    ) -> MakeStack<MakeCache<M, Tgt, M::Response, SystemClock>>
+    where
+        M: Service<Tgt>,
+        M::Response: Clone,
+        Tgt: Hash + Eq,
+        MakeCache<M, Tgt, M::Response, SystemClock>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push_cache_with_clock::<Tgt, Req, _>(idle_timeout, SystemClock)
+    }
+
+    /// The same as `push_cache` but reads the time from `clock`.
+    pub fn push_cache_with_clock<Tgt, Req, C>(
+        self,
+        idle_timeout: Duration,
+        clock: C,
+    ) -> MakeStack<MakeCache<M, Tgt, M::Response, C>>
+    where
+        M: Service<Tgt>,
+        M::Response: Clone,
+        Tgt: Hash + Eq,
+        C: Clock + Clone,
+        MakeCache<M, Tgt, M::Response, C>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeCacheLayer::new(idle_timeout, clock))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        convert::Infallible,
+        future::{ready, Ready},
+        pin::pin,
+        rc::Rc,
+    };
+
+    use futures::pin_mut;
+    use pipeline_base::{ManualClock, Stack};
+    use pipeline_test::{assert_pending, assert_ready_err, assert_ready_ok, noop_context};
+
+    use super::*;
+
+    /// Echoes the request prefixed by the id of the service.
+    #[derive(Clone, Debug)]
+    struct IdService(usize);
+    impl Service<String> for IdService {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: String) -> Self::Future {
+            ready(Ok(format!("{}:{}", self.0, req)))
+        }
+    }
+
+    /// Makes `IdService` with increasing ids.
+    #[derive(Clone)]
+    struct MakeId(Rc<Cell<usize>>);
+    impl<Tgt> Service<Tgt> for MakeId {
+        type Response = IdService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: Tgt) -> Self::Future {
+            let id = self.0.get();
+            self.0.set(id + 1);
+            ready(Ok(IdService(id)))
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        let made = Rc::new(Cell::new(0));
+        let clock = ManualClock::new();
+
+        let stack = Stack::new(MakeId(made.clone()));
+        let make_stack = MakeStack::new::<&str>(stack)
+            .push_cache_with_clock::<&str, String, _>(Duration::from_secs(10), clock.clone())
+            .check_make_clone::<&str, String>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let mut make = |target| {
+            let cx = &mut noop_context();
+            assert_ready_ok!(tower::MakeService::<&str, String>::poll_ready(
+                &mut make_svc,
+                cx
+            ));
+            assert_ready_ok!(pin!(make_svc.call(target)).poll(cx))
+        };
+
+        let mut svc = make("a");
+        let cx = &mut noop_context();
+        let resp = assert_ready_ok!(pin!(svc.call("hello".to_string())).poll(cx));
+        assert_eq!(resp, "0:hello");
+
+        // Reuse the cached service.
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(make("a").0, 0);
+        assert_eq!(make("b").0, 1);
+        assert_eq!(made.get(), 2);
+
+        // The service for "a" was last handed out 11 seconds ago.
+        clock.advance(Duration::from_secs(7));
+        assert_eq!(make("b").0, 1);
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(make("b").0, 1);
+        assert_eq!(make("a").0, 2);
+        assert_eq!(made.get(), 3);
+    }
+
+    #[test]
+    fn test_cache_overlapping_makes() {
+        let (mock, handle) = pipeline_test::pair::<&str, IdService>();
+        let mut make_svc = MakeCache::new(mock, Duration::from_secs(10), ManualClock::new());
+        let cx = &mut noop_context();
+
+        let first = make_svc.call("a");
+        let second = make_svc.call("a");
+        pin_mut!(first);
+        pin_mut!(second);
+        assert_pending!(first.as_mut().poll(cx));
+        assert_pending!(second.as_mut().poll(cx));
+
+        // Only the first call made a service; the second waits for it.
+        let (target, send) = handle.next_request().expect("make started");
+        assert_eq!(target, "a");
+        assert!(handle.next_request().is_none());
+        send.send_response(IdService(7));
+        assert_eq!(assert_ready_ok!(first.poll(cx)).0, 7);
+        assert_eq!(assert_ready_ok!(second.poll(cx)).0, 7);
+
+        // A failed make fails every waiting call and is not cached.
+        let first = make_svc.call("b");
+        let second = make_svc.call("b");
+        pin_mut!(first);
+        pin_mut!(second);
+        assert_pending!(first.as_mut().poll(cx));
+        let (_, send) = handle.next_request().expect("make started");
+        assert!(handle.next_request().is_none());
+        send.send_error("refused");
+        assert_ready_err!(first.poll(cx));
+        assert_ready_err!(second.poll(cx));
+        let retry = make_svc.call("b");
+        assert!(handle.next_request().is_some());
+        drop(retry);
+    }
+}
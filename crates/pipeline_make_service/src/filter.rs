@@ -0,0 +1,94 @@
+use pipeline_base::{Filter, FilterLayer};
+
+use crate::{MakeServiceFor, MakeStack};
+
+impl<M> MakeStack<M> {
+    /// Check the target before it reaches the current stack.
+    ///
+    /// No service is made for a rejected target. The make error is boxed.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_filter<Tgt, Req, P>(self, predicate: P) -> MakeStack<Filter<M, P>>
+    where
+        P: Clone,
+        Filter<M, P>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(FilterLayer::new(predicate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        convert::Infallible,
+        fmt,
+        future::{ready, Future, Ready},
+        pin::pin,
+        rc::Rc,
+        task::{Context, Poll},
+    };
+
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_err, assert_ready_ok, noop_context, EchoService};
+    use tower::Service;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Unauthorized(String);
+    impl fmt::Display for Unauthorized {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "route {} is not authorized", self.0)
+        }
+    }
+    impl std::error::Error for Unauthorized {}
+
+    /// Makes `EchoService` and counts the services made.
+    struct MakeEcho(Rc<Cell<usize>>);
+    impl<Tgt> Service<Tgt> for MakeEcho {
+        type Response = EchoService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: Tgt) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            ready(Ok(EchoService))
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let made = Rc::new(Cell::new(0));
+
+        let stack = Stack::new(MakeEcho(made.clone()));
+        let make_stack =
+            MakeStack::new::<String>(stack).push_filter::<String, String, _>(|route: String| {
+                if route.starts_with("/admin") {
+                    return Err(Unauthorized(route));
+                }
+                Ok(route)
+            });
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(tower::MakeService::<String, String>::poll_ready(
+            &mut make_svc,
+            cx
+        ));
+
+        // Make a service for an authorized route.
+        assert_ready_ok!(pin!(make_svc.call("/index".to_string())).poll(cx));
+        assert_eq!(made.get(), 1);
+
+        // Reject an unauthorized route.
+        let error = assert_ready_err!(pin!(make_svc.call("/admin/users".to_string())).poll(cx));
+        let error = error.downcast::<Unauthorized>().unwrap();
+        assert_eq!(error.0, "/admin/users");
+        assert_eq!(made.get(), 1);
+    }
+}
@@ -0,0 +1,293 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+use tracing::{instrument::Instrumented, Instrument as _, Span};
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// Derives the span of the services made for a target.
+pub trait GetSpan<Tgt> {
+    fn get_span(&self, target: &Tgt) -> Span;
+}
+impl<F, Tgt> GetSpan<Tgt> for F
+where
+    F: Fn(&Tgt) -> Span,
+{
+    fn get_span(&self, target: &Tgt) -> Span {
+        self(target)
+    }
+}
+
+/// Wraps every service made by `M` in the span of its target.
+///
+/// The span is entered while the target is handed to `M` as well.
+///
+/// `M`: a thing that makes services
+#[derive(Clone, Debug)]
+pub struct MakeInstrument<G, M> {
+    get_span: G,
+    inner: M,
+}
+impl<G, M, Tgt> Service<Tgt> for MakeInstrument<G, M>
+where
+    G: GetSpan<Tgt>,
+    M: Service<Tgt>,
+{
+    type Response = Instrument<M::Response>;
+    type Error = M::Error;
+    type Future = MakeInstrumentFuture<M::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let span = self.get_span.get_span(&target);
+        let future = {
+            let _enter = span.enter();
+            self.inner.call(target)
+        };
+        MakeInstrumentFuture {
+            future,
+            span: Some(span),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MakeInstrumentFuture<F> {
+        #[pin]
+        future: F,
+        span: Option<Span>,
+    }
+}
+impl<F, S, E> Future for MakeInstrumentFuture<F>
+where
+    F: Future<Output = Result<S, E>>,
+{
+    type Output = Result<Instrument<S>, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = {
+            let _enter = this.span.as_ref().map(Span::enter);
+            match this.future.poll(cx) {
+                Poll::Ready(res) => res,
+                Poll::Pending => return Poll::Pending,
+            }
+        };
+        let span = this.span.take().expect("polled after completion");
+        Poll::Ready(res.map(|inner| Instrument { inner, span }))
+    }
+}
+
+/// A service that enters its span for `poll_ready`, `call` and the response future.
+#[derive(Clone, Debug)]
+pub struct Instrument<S> {
+    inner: S,
+    span: Span,
+}
+impl<S> Instrument<S> {
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+impl<S, Req> Service<Req> for Instrument<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let _enter = self.span.enter();
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let _enter = self.span.enter();
+        self.inner.call(req).instrument(self.span.clone())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MakeInstrumentLayer<G>(G);
+impl<G> MakeInstrumentLayer<G> {
+    pub fn new(get_span: G) -> Self {
+        Self(get_span)
+    }
+}
+impl<G: Clone, M> Layer<M> for MakeInstrumentLayer<G> {
+    type Service = MakeInstrument<G, M>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeInstrument {
+            get_span: self.0.clone(),
+            inner,
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Wrap every made service in a span derived from its target.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_instrument<Tgt, Req, G>(self, get_span: G) -> MakeStack<MakeInstrument<G, M>>
+    where
+        G: Clone,
+        MakeInstrument<G, M>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeInstrumentLayer::new(get_span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        fmt,
+        future::{ready, Ready},
+        pin::pin,
+        sync::{Arc, Mutex},
+    };
+
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_ok, noop_context};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id},
+        Event, Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{self, SubscriberExt},
+        registry::LookupSpan,
+        Registry,
+    };
+
+    use super::*;
+
+    /// Responds with the request and logs each step.
+    struct LogService;
+    impl Service<String> for LogService {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            tracing::info!("poll_ready");
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: String) -> Self::Future {
+            tracing::info!("call");
+            Box::pin(async move {
+                tracing::info!("respond");
+                Ok(req)
+            })
+        }
+    }
+
+    struct MakeLog;
+    impl Service<&'static str> for MakeLog {
+        type Response = LogService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: &'static str) -> Self::Future {
+            tracing::info!("make");
+            ready(Ok(LogService))
+        }
+    }
+
+    /// The `addr` field of a span
+    struct Addr(String);
+
+    /// Records the `addr` and the `message` fields.
+    #[derive(Default)]
+    struct Fields {
+        addr: Option<String>,
+        message: Option<String>,
+    }
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            match field.name() {
+                "addr" => self.addr = Some(format!("{value:?}")),
+                "message" => self.message = Some(format!("{value:?}")),
+                _ => (),
+            }
+        }
+    }
+
+    /// Records every event as "<addr of the current span> <message>".
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+    impl<S> layer::Layer<S> for Recorder
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: layer::Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            if let Some(addr) = fields.addr {
+                ctx.span(id).unwrap().extensions_mut().insert(Addr(addr));
+            }
+        }
+        fn on_event(&self, event: &Event<'_>, ctx: layer::Context<'_, S>) {
+            let addr = ctx
+                .event_span(event)
+                .and_then(|span| span.extensions().get::<Addr>().map(|addr| addr.0.clone()))
+                .unwrap_or_else(|| "-".to_string());
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let message = fields.message.unwrap_or_default();
+            self.0.lock().unwrap().push(format!("{addr} {message}"));
+        }
+    }
+
+    #[test]
+    fn test_instrument() {
+        let recorder = Recorder::default();
+        let subscriber = Registry::default().with(recorder.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let make_stack = MakeStack::new::<&str>(Stack::new(MakeLog))
+                .push_instrument::<&str, String, _>(
+                    |addr: &&str| tracing::info_span!("endpoint", addr = %addr),
+                );
+            let mut make_svc = make_stack.into_inner().into_inner();
+
+            for addr in ["10.0.0.1:80", "10.0.0.2:80"] {
+                // Make the service.
+                let cx = &mut noop_context();
+                let mut svc = assert_ready_ok!(pin!(make_svc.call(addr)).poll(cx));
+
+                // Call the service.
+                assert_ready_ok!(svc.poll_ready(cx));
+                let fut = svc.call("hello".to_string());
+                tracing::info!("outside");
+                let resp = assert_ready_ok!(pin!(fut).poll(cx));
+                assert_eq!(resp, "hello");
+            }
+        });
+
+        let expected = [
+            "10.0.0.1:80 make",
+            "10.0.0.1:80 poll_ready",
+            "10.0.0.1:80 call",
+            "- outside",
+            "10.0.0.1:80 respond",
+            "10.0.0.2:80 make",
+            "10.0.0.2:80 poll_ready",
+            "10.0.0.2:80 call",
+            "- outside",
+            "10.0.0.2:80 respond",
+        ];
+        assert_eq!(*recorder.0.lock().unwrap(), expected);
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::Param;
+use tower::{Layer, Service};
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// A service made for a target, labeled with a parameter of that target.
+#[derive(Clone, Debug)]
+pub struct Labeled<S, L> {
+    inner: S,
+    label: L,
+}
+impl<S, L> Labeled<S, L> {
+    pub fn label(&self) -> &L {
+        &self.label
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+impl<S, L, Req> Service<Req> for Labeled<S, L>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+/// Labels every service made by `M` with the `L` parameter of its target.
+///
+/// `M`: a thing that makes services
+#[derive(Clone, Debug)]
+pub struct MakeLabel<M, L> {
+    inner: M,
+    _label: PhantomData<fn() -> L>,
+}
+impl<M, L, Tgt> Service<Tgt> for MakeLabel<M, L>
+where
+    Tgt: Param<L>,
+    M: Service<Tgt>,
+{
+    type Response = Labeled<M::Response, L>;
+    type Error = M::Error;
+    type Future = MakeLabelFuture<M::Future, L>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let label = target.param();
+        MakeLabelFuture {
+            future: self.inner.call(target),
+            label: Some(label),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MakeLabelFuture<F, L> {
+        #[pin]
+        future: F,
+        label: Option<L>,
+    }
+}
+impl<F, L, S, E> Future for MakeLabelFuture<F, L>
+where
+    F: Future<Output = Result<S, E>>,
+{
+    type Output = Result<Labeled<S, L>, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let inner = match this.future.poll(cx) {
+            Poll::Ready(Ok(inner)) => inner,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        let label = this.label.take().expect("polled after completion");
+        Poll::Ready(Ok(Labeled { inner, label }))
+    }
+}
+
+#[derive(Debug)]
+pub struct MakeLabelLayer<L>(PhantomData<fn() -> L>);
+impl<L> MakeLabelLayer<L> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+impl<L> Default for MakeLabelLayer<L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<L> Clone for MakeLabelLayer<L> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+impl<M, L> Layer<M> for MakeLabelLayer<L> {
+    type Service = MakeLabel<M, L>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeLabel {
+            inner,
+            _label: PhantomData,
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Label every made service with the `L` parameter of its target.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_label<Tgt, Req, L>(self) -> MakeStack<MakeLabel<M, L>>
+    where
+        MakeLabel<M, L>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeLabelLayer::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Future},
+        pin::pin,
+    };
+
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+    use tower::service_fn;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Zone(&'static str);
+
+    struct Endpoint {
+        port: u16,
+        zone: Zone,
+    }
+    impl Param<Zone> for Endpoint {
+        fn param(&self) -> Zone {
+            self.zone.clone()
+        }
+    }
+    impl Param<u16> for Endpoint {
+        fn param(&self) -> u16 {
+            self.port
+        }
+    }
+
+    #[test]
+    fn test_label() {
+        // The same layer is used with two different parameters of the same target.
+        let make_echo = service_fn(|_: Endpoint| ready(Ok::<_, Infallible>(EchoService)));
+        let make_stack = MakeStack::new::<Endpoint>(Stack::new(make_echo))
+            .push_label::<Endpoint, String, Zone>()
+            .push_label::<Endpoint, String, u16>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let target = Endpoint {
+            port: 8080,
+            zone: Zone("west"),
+        };
+        let cx = &mut noop_context();
+        let mut svc = assert_ready_ok!(pin!(make_svc.call(target)).poll(cx));
+        assert_eq!(*svc.label(), 8080);
+
+        let resp = assert_ready_ok!(pin!(svc.call("hello".to_string())).poll(cx));
+        assert_eq!(resp, "hello");
+
+        let svc = svc.into_inner();
+        assert_eq!(*svc.label(), Zone("west"));
+    }
+}
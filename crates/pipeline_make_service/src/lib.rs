@@ -1,20 +1,90 @@
-use pipeline_base::Stack;
+use pipeline_base::{Describe, Description, Layers, SendSyncStack, ServiceFor, Stack, UnpinStack};
 use tower::{Layer, MakeService, Service};
 
+mod boxed;
+mod cache;
+mod filter;
+#[cfg(feature = "tracing")]
+mod instrument;
+mod label;
+mod map_err;
+mod map_target;
+mod metrics;
+mod new_service;
 mod on_service;
+#[cfg(feature = "tokio")]
+mod queue;
+mod response;
+mod router;
+mod switch;
+#[cfg(feature = "tokio")]
+mod timeout;
 
-pub use on_service::{OnService, OnServiceLayer};
+pub use boxed::{BoxMakeService, UnsyncBoxMakeService};
+pub use cache::{MakeCache, MakeCacheFuture, MakeCacheLayer};
+#[cfg(feature = "tracing")]
+pub use instrument::{
+    GetSpan, Instrument, MakeInstrument, MakeInstrumentFuture, MakeInstrumentLayer,
+};
+pub use label::{Labeled, MakeLabel, MakeLabelFuture, MakeLabelLayer};
+pub use metrics::{
+    Labels, MakeMetrics, MakeMetricsFuture, MakeMetricsLayer, Metrics, MetricsFuture, Registry,
+    DEFAULT_BUCKETS,
+};
+pub use new_service::{IntoNewService, LazyFuture, LazyService, MakeError};
+pub use on_service::{OnService, OnServiceFuture, OnServiceLayer};
+#[cfg(feature = "tokio")]
+pub use queue::{
+    BoxWorker, Executor, MakeQueue, MakeQueueFuture, MakeQueueLayer, Queue, QueueClosedError,
+    QueueConfig, QueueFuture, TokioExecutor,
+};
+pub use response::{
+    MakeMapResponse, MakeMapResponseFuture, MakeMapResponseLayer, TargetMapResponse,
+    TargetMapResponseFuture,
+};
+pub use router::{
+    MakeRouter, MakeRouterLayer, RecognizeRoute, Route, RoutePoisoned, Router, RouterFuture,
+};
+pub use switch::MakeSwitch;
+#[cfg(feature = "tokio")]
+pub use timeout::{
+    MakeRequestTimeout, MakeRequestTimeoutFuture, MakeRequestTimeoutLayer, MakeServiceTimeout,
+    MakeServiceTimeoutLayer, MakeTimeout, RequestTimeout, ResponseTimeout, TimeoutFuture,
+    TimeoutService,
+};
+
+/// A stack that makes services of `Req` for targets `Tgt`.
+///
+/// Implemented for every such `MakeService`; it only exists so the `MakeStack` helpers fail with an error naming the stack.
+#[diagnostic::on_unimplemented(
+    message = "the stack `{Self}` does not make services of `{Req}` for `{Tgt}`",
+    label = "this stack does not make services of `{Req}` for `{Tgt}`",
+    note = "the outermost type is the layer pushed last; check the `Tgt` and `Req` it was pushed with"
+)]
+pub trait MakeServiceFor<Tgt, Req>: MakeService<Tgt, Req> + Service<Tgt> {}
+impl<M, Tgt, Req> MakeServiceFor<Tgt, Req> for M
+where
+    M: Service<Tgt>,
+    M::Response: ServiceFor<Req>,
+{
+}
 
 /// `M`: a thing that makes services
 pub struct MakeStack<M>(Stack<M>);
 
+impl<M> Describe for MakeStack<M> {
+    fn describe(&self) -> Description {
+        self.0.describe()
+    }
+}
+
 impl<M> MakeStack<M> {
     /// Create a new `MakeStack`.
     ///
     /// `Tgt`: the input type of the inner service
     pub fn new<Tgt>(stack: Stack<M>) -> Self
     where
-        M: Service<Tgt>,
+        M: ServiceFor<Tgt>,
     {
         MakeStack(stack).check()
     }
@@ -31,17 +101,31 @@ impl<M> MakeStack<M> {
     pub fn push<Tgt, Req, L>(self, layer: L) -> MakeStack<L::Service>
     where
         L: Layer<M>,
-        L::Service: MakeService<Tgt, Req> + Service<Tgt>,
+        L::Service: MakeServiceFor<Tgt, Req>,
     {
         let stack = self.into_inner();
         let stack = stack.push(layer);
         MakeStack::new::<Tgt>(stack).check_make()
     }
 
+    /// Push all the layers onto the stack, in the order they were pushed onto `layers`.
+    ///
+    /// `Tgt`: the target type after the layers are applied
+    ///
+    /// `Req`: the request type after the layers are applied
+    pub fn push_layers<Tgt, Req, L>(self, layers: Layers<L>) -> MakeStack<L::Service>
+    where
+        L: Layer<M>,
+        L::Service: MakeServiceFor<Tgt, Req>,
+    {
+        let stack = self.into_inner().push_layers(layers);
+        MakeStack::new::<Tgt>(stack).check_make()
+    }
+
     /// Make sure the inner service is a certain `Service`.
     pub fn check<Tgt>(self) -> Self
     where
-        M: Service<Tgt>,
+        M: ServiceFor<Tgt>,
     {
         self
     }
@@ -49,7 +133,7 @@ impl<M> MakeStack<M> {
     /// Make sure the inner service is a certain `MakeService`.
     pub fn check_make<Tgt, Req>(self) -> Self
     where
-        M: MakeService<Tgt, Req>,
+        M: MakeServiceFor<Tgt, Req>,
     {
         self
     }
@@ -57,8 +141,87 @@ impl<M> MakeStack<M> {
     /// Make sure the inner service is a certain `MakeService` and is `Clone`.
     pub fn check_make_clone<Tgt, Req>(self) -> Self
     where
-        M: MakeService<Tgt, Req> + Clone,
+        M: MakeServiceFor<Tgt, Req> + Clone,
+    {
+        self
+    }
+
+    /// Make sure the services made for `Tgt` respond to `Req` with `Resp`.
+    pub fn check_make_service_response<Tgt, Req, Resp>(self) -> Self
+    where
+        M: MakeServiceFor<Tgt, Req>,
+        <M as MakeService<Tgt, Req>>::Service: Service<Req, Response = Resp>,
     {
         self
     }
+
+    /// Make sure the stack can be shared with other threads and hands out services that can be sent to them.
+    ///
+    /// The inner service must be `Send + Sync`, its futures for `Tgt` must be `Send`, and the services it makes must be
+    /// `Send` with `Send` futures for `Req`.
+    pub fn check_make_service_send<Tgt, Req>(self) -> Self
+    where
+        M: ServiceFor<Tgt> + SendSyncStack,
+        M::Future: Send,
+        M::Response: Service<Req> + Send,
+        <M::Response as Service<Req>>::Future: Send,
+    {
+        self
+    }
+
+    /// Make sure the services made for `Tgt` are `Clone`.
+    pub fn check_service_clone<Tgt>(self) -> Self
+    where
+        M: ServiceFor<Tgt>,
+        M::Response: Clone,
+    {
+        self
+    }
+
+    /// Make sure the inner service is `Unpin`.
+    pub fn check_unpin(self) -> Self
+    where
+        M: UnpinStack,
+    {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline_base::MapTargetLayer;
+    use pipeline_test::{pair, Mock};
+
+    use super::*;
+
+    #[test]
+    fn test_checks() {
+        let (make, _handle) = pair::<String, Mock<u8, u16>>();
+        MakeStack::new::<String>(Stack::new(make))
+            .check_make_clone::<String, u8>()
+            .check_make_service_response::<String, u8, u16>()
+            .check_make_service_send::<String, u8>()
+            .check_service_clone::<String>()
+            .check_unpin();
+    }
+
+    #[test]
+    fn test_push_layers() {
+        let (make, _handle) = pair::<String, Mock<u8, u16>>();
+        let layers = Layers::new()
+            .push_named(
+                "to_addr",
+                MapTargetLayer::new(|port: u16| format!("localhost:{port}")),
+            )
+            .push_option(None::<MapTargetLayer<fn(u16) -> u16>>);
+        let mut description = Description::new();
+        description.push("mock");
+        let make_stack = MakeStack::new::<String>(Stack::from_parts(make, Some(description)))
+            .push_layers::<u16, u8, _>(layers)
+            .check_make_service_response::<u16, u8, u16>();
+        assert_eq!(
+            make_stack.describe().names().collect::<Vec<_>>(),
+            ["mock", "to_addr"]
+        );
+    }
 }
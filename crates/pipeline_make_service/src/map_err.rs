@@ -0,0 +1,96 @@
+use pipeline_base::{MapErr, MapErrBoxed, MapErrBoxedLayer, MapErrLayer};
+
+use crate::{MakeServiceFor, MakeStack};
+
+impl<M> MakeStack<M> {
+    /// Convert the errors of making services.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_map_err<Tgt, Req, F>(self, f: F) -> MakeStack<MapErr<M, F>>
+    where
+        F: Clone,
+        MapErr<M, F>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MapErrLayer::new(f))
+    }
+
+    /// Box the errors of making services into `pipeline_base::Error`.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_map_err_boxed<Tgt, Req>(self) -> MakeStack<MapErrBoxed<M>>
+    where
+        MapErrBoxed<M>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MapErrBoxedLayer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fmt,
+        future::{ready, Future, Ready},
+        pin::pin,
+        task::{Context, Poll},
+    };
+
+    use pipeline_base::{find_cause, Stack};
+    use pipeline_test::{assert_ready_err, assert_ready_ok, noop_context, EchoService};
+    use tower::Service;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct Unreachable(u16);
+    impl fmt::Display for Unreachable {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "port {} is unreachable", self.0)
+        }
+    }
+    impl std::error::Error for Unreachable {}
+
+    /// Makes `EchoService` for even ports only.
+    struct MakeEven;
+    impl Service<u16> for MakeEven {
+        type Response = EchoService;
+        type Error = Unreachable;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, port: u16) -> Self::Future {
+            if port % 2 == 0 {
+                ready(Ok(EchoService))
+            } else {
+                ready(Err(Unreachable(port)))
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_err() {
+        let make_stack = MakeStack::new::<u16>(Stack::new(MakeEven))
+            .push_map_err::<u16, String, _>(|Unreachable(port)| format!("{port} is odd"));
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let cx = &mut noop_context();
+        let error = assert_ready_err!(pin!(make_svc.call(8081)).poll(cx));
+        assert_eq!(error, "8081 is odd");
+    }
+
+    #[test]
+    fn test_map_err_boxed() {
+        let make_stack =
+            MakeStack::new::<u16>(Stack::new(MakeEven)).push_map_err_boxed::<u16, String>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(pin!(make_svc.call(8080)).poll(cx));
+        let error = assert_ready_err!(pin!(make_svc.call(8081)).poll(cx));
+        assert_eq!(find_cause::<Unreachable>(&*error), Some(&Unreachable(8081)));
+    }
+}
@@ -0,0 +1,90 @@
+use pipeline_base::{MapTarget, MapTargetLayer};
+
+use crate::{MakeServiceFor, MakeStack};
+
+impl<M> MakeStack<M> {
+    /// Convert the target before it reaches the current stack.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_map_target<Tgt, Req, F>(self, f: F) -> MakeStack<MapTarget<M, F>>
+    where
+        F: Clone,
+        MapTarget<M, F>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MapTargetLayer::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Future},
+        pin::{pin, Pin},
+        task::{Context, Poll},
+    };
+
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService, TraceBody, TraceService};
+    use tower::{service_fn, Layer, Service};
+
+    use super::*;
+
+    /// Wraps every made service in a `TraceService` whose request mark starts with the target it sees.
+    struct MakeTrace<M> {
+        inner: M,
+        req_mark: &'static str,
+    }
+    impl<M, Tgt> Service<Tgt> for MakeTrace<M>
+    where
+        Tgt: ToString,
+        M: Service<Tgt>,
+        M::Future: 'static,
+    {
+        type Response = TraceService<M::Response>;
+        type Error = M::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.inner.poll_ready(cx)
+        }
+        fn call(&mut self, target: Tgt) -> Self::Future {
+            let req_mark = format!("{} {}", target.to_string(), self.req_mark);
+            let fut = self.inner.call(target);
+            Box::pin(async move { Ok(TraceService::new(fut.await?, req_mark, "")) })
+        }
+    }
+
+    struct MakeTraceLayer {
+        req_mark: &'static str,
+    }
+    impl<M> Layer<M> for MakeTraceLayer {
+        type Service = MakeTrace<M>;
+        fn layer(&self, inner: M) -> Self::Service {
+            MakeTrace {
+                inner,
+                req_mark: self.req_mark,
+            }
+        }
+    }
+
+    #[test]
+    fn test_map_target() {
+        let make_echo = service_fn(|_: String| ready(Ok::<_, Infallible>(EchoService)));
+        let make_stack = MakeStack::new::<String>(Stack::new(make_echo))
+            .push::<String, TraceBody, _>(MakeTraceLayer { req_mark: "req_1" })
+            .push_map_target::<u16, TraceBody, _>(|port: u16| format!("localhost:{port}"))
+            .push::<u16, TraceBody, _>(MakeTraceLayer { req_mark: "req_2" });
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(tower::MakeService::<u16, TraceBody>::poll_ready(
+            &mut make_svc,
+            cx
+        ));
+        let mut svc = assert_ready_ok!(pin!(make_svc.call(8080)).poll(cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(resp.history, ["8080 req_2", "localhost:8080 req_1", "", ""]);
+    }
+}
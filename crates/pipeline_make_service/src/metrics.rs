@@ -0,0 +1,496 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{Clock, Param, SystemClock};
+use tower::{Layer, Service};
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// The default upper bounds of the latency buckets in seconds
+pub const DEFAULT_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The labels a target is accounted under.
+///
+/// The labels are rendered in the order they are added.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Labels(Vec<(String, String)>);
+impl Labels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.push((key.into(), value.into()));
+        self
+    }
+
+    /// Write the labels and the `extra` label, if any, in the Prometheus text format.
+    fn write(&self, out: &mut String, extra: Option<(&str, &str)>) {
+        let labels = self
+            .0
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .chain(extra);
+        let mut first = true;
+        for (key, value) in labels {
+            out.push(if first { '{' } else { ',' });
+            first = false;
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            let _ = write!(out, "{key}=\"{value}\"");
+        }
+        if !first {
+            out.push('}');
+        }
+    }
+}
+
+/// The metrics of the requests to the services of one set of labels.
+#[derive(Debug)]
+struct TargetMetrics {
+    requests: u64,
+    errors: u64,
+    /// The number of finished requests
+    finished: u64,
+    /// The number of finished requests per latency bucket, not cumulative
+    ///
+    /// Requests slower than the largest bucket are only counted in `finished`.
+    buckets: Vec<u64>,
+    latency_sum: f64,
+}
+
+/// Aggregates the metrics of all the services made through `MakeMetrics`.
+///
+/// Clones share the same metrics.
+pub struct Registry<C = SystemClock> {
+    families: Arc<Mutex<BTreeMap<Labels, Arc<Mutex<TargetMetrics>>>>>,
+    buckets: Arc<[f64]>,
+    clock: C,
+}
+impl Registry {
+    pub fn new() -> Self {
+        Self::with_buckets(&DEFAULT_BUCKETS, SystemClock)
+    }
+}
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<C> Registry<C> {
+    /// `buckets`: the upper bounds of the latency buckets in seconds, in increasing order
+    ///
+    /// Panics if the bounds are not strictly increasing.
+    pub fn with_buckets(buckets: &[f64], clock: C) -> Self {
+        assert!(
+            buckets.windows(2).all(|pair| pair[0] < pair[1]),
+            "latency buckets must be strictly increasing: {buckets:?}"
+        );
+        Self {
+            families: Default::default(),
+            buckets: buckets.into(),
+            clock,
+        }
+    }
+
+    fn metrics(&self, labels: Labels) -> Arc<Mutex<TargetMetrics>> {
+        let mut families = self.families.lock().unwrap();
+        families
+            .entry(labels)
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(TargetMetrics {
+                    requests: 0,
+                    errors: 0,
+                    finished: 0,
+                    buckets: vec![0; self.buckets.len()],
+                    latency_sum: 0.0,
+                }))
+            })
+            .clone()
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap();
+        let snapshot = families
+            .iter()
+            .map(|(labels, metrics)| (labels, metrics.lock().unwrap()))
+            .collect::<Vec<_>>();
+        let mut out = String::new();
+
+        out.push_str("# HELP requests_total The number of requests.\n");
+        out.push_str("# TYPE requests_total counter\n");
+        for (labels, metrics) in &snapshot {
+            out.push_str("requests_total");
+            labels.write(&mut out, None);
+            let _ = writeln!(out, " {}", metrics.requests);
+        }
+
+        out.push_str("# HELP request_errors_total The number of failed requests.\n");
+        out.push_str("# TYPE request_errors_total counter\n");
+        for (labels, metrics) in &snapshot {
+            out.push_str("request_errors_total");
+            labels.write(&mut out, None);
+            let _ = writeln!(out, " {}", metrics.errors);
+        }
+
+        out.push_str("# HELP request_duration_seconds The latency of the requests.\n");
+        out.push_str("# TYPE request_duration_seconds histogram\n");
+        for (labels, metrics) in &snapshot {
+            let mut cumulative = 0;
+            for (bound, count) in self.buckets.iter().zip(&metrics.buckets) {
+                cumulative += count;
+                out.push_str("request_duration_seconds_bucket");
+                labels.write(&mut out, Some(("le", &bound.to_string())));
+                let _ = writeln!(out, " {cumulative}");
+            }
+            out.push_str("request_duration_seconds_bucket");
+            labels.write(&mut out, Some(("le", "+Inf")));
+            let _ = writeln!(out, " {}", metrics.finished);
+            out.push_str("request_duration_seconds_sum");
+            labels.write(&mut out, None);
+            let _ = writeln!(out, " {}", metrics.latency_sum);
+            out.push_str("request_duration_seconds_count");
+            labels.write(&mut out, None);
+            let _ = writeln!(out, " {}", metrics.finished);
+        }
+        out
+    }
+}
+impl<C: Clone> Clone for Registry<C> {
+    fn clone(&self) -> Self {
+        Self {
+            families: self.families.clone(),
+            buckets: self.buckets.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+impl<C> fmt::Debug for Registry<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("buckets", &self.buckets)
+            .finish()
+    }
+}
+
+/// The handle of a made service to the metrics of its labels.
+struct Recorder<C> {
+    metrics: Arc<Mutex<TargetMetrics>>,
+    buckets: Arc<[f64]>,
+    clock: C,
+}
+impl<C: Clone> Clone for Recorder<C> {
+    fn clone(&self) -> Self {
+        Self {
+            metrics: self.metrics.clone(),
+            buckets: self.buckets.clone(),
+            clock: self.clock.clone(),
+        }
+    }
+}
+impl<C: Clock> Recorder<C> {
+    fn start(&self) -> Instant {
+        self.metrics.lock().unwrap().requests += 1;
+        self.clock.now()
+    }
+
+    fn finish(&self, start: Instant, failed: bool) {
+        let latency = self
+            .clock
+            .now()
+            .saturating_duration_since(start)
+            .as_secs_f64();
+        let mut metrics = self.metrics.lock().unwrap();
+        if failed {
+            metrics.errors += 1;
+        }
+        metrics.finished += 1;
+        metrics.latency_sum += latency;
+        if let Some(i) = self.buckets.iter().position(|bound| latency <= *bound) {
+            metrics.buckets[i] += 1;
+        }
+    }
+}
+
+/// Accounts the requests to every service made by `M` under the `Labels` of its target.
+///
+/// `M`: a thing that makes services
+#[derive(Clone, Debug)]
+pub struct MakeMetrics<M, C = SystemClock> {
+    inner: M,
+    registry: Registry<C>,
+}
+impl<M, C, Tgt> Service<Tgt> for MakeMetrics<M, C>
+where
+    Tgt: Param<Labels>,
+    M: Service<Tgt>,
+    C: Clone,
+{
+    type Response = Metrics<M::Response, C>;
+    type Error = M::Error;
+    type Future = MakeMetricsFuture<M::Future, C>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let recorder = Recorder {
+            metrics: self.registry.metrics(target.param()),
+            buckets: self.registry.buckets.clone(),
+            clock: self.registry.clock.clone(),
+        };
+        MakeMetricsFuture {
+            future: self.inner.call(target),
+            recorder: Some(recorder),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MakeMetricsFuture<F, C> {
+        #[pin]
+        future: F,
+        recorder: Option<Recorder<C>>,
+    }
+}
+impl<F, C, S, E> Future for MakeMetricsFuture<F, C>
+where
+    F: Future<Output = Result<S, E>>,
+{
+    type Output = Result<Metrics<S, C>, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let recorder = this.recorder.take().expect("polled after completion");
+        Poll::Ready(res.map(|inner| Metrics { inner, recorder }))
+    }
+}
+
+/// A service whose requests are accounted in a `Registry`.
+pub struct Metrics<S, C> {
+    inner: S,
+    recorder: Recorder<C>,
+}
+impl<S: Clone, C: Clone> Clone for Metrics<S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+impl<S: fmt::Debug, C> fmt::Debug for Metrics<S, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+impl<S, C, Req> Service<Req> for Metrics<S, C>
+where
+    S: Service<Req>,
+    C: Clock + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MetricsFuture<S::Future, C>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let start = self.recorder.start();
+        MetricsFuture {
+            future: self.inner.call(req),
+            recorder: Some(self.recorder.clone()),
+            start,
+        }
+    }
+}
+
+pin_project! {
+    pub struct MetricsFuture<F, C> {
+        #[pin]
+        future: F,
+        recorder: Option<Recorder<C>>,
+        start: Instant,
+    }
+}
+impl<F, C, T, E> Future for MetricsFuture<F, C>
+where
+    F: Future<Output = Result<T, E>>,
+    C: Clock,
+{
+    type Output = Result<T, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let recorder = this.recorder.take().expect("polled after completion");
+        recorder.finish(*this.start, res.is_err());
+        Poll::Ready(res)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MakeMetricsLayer<C = SystemClock>(Registry<C>);
+impl<C> MakeMetricsLayer<C> {
+    pub fn new(registry: Registry<C>) -> Self {
+        Self(registry)
+    }
+}
+impl<C: Clone, M> Layer<M> for MakeMetricsLayer<C> {
+    type Service = MakeMetrics<M, C>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeMetrics {
+            inner,
+            registry: self.0.clone(),
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Account the requests to every made service in `registry` under the `Labels` of its target.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_metrics<Tgt, Req, C>(self, registry: Registry<C>) -> MakeStack<MakeMetrics<M, C>>
+    where
+        C: Clone,
+        MakeMetrics<M, C>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeMetricsLayer::new(registry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::{ready, Ready},
+        pin::pin,
+        time::Duration,
+    };
+
+    use pipeline_base::{Error, ManualClock, Stack};
+    use pipeline_test::{assert_ready, assert_ready_ok, noop_context};
+
+    use super::*;
+
+    struct Endpoint {
+        addr: &'static str,
+    }
+    impl Param<Labels> for Endpoint {
+        fn param(&self) -> Labels {
+            Labels::new().with("addr", self.addr)
+        }
+    }
+
+    /// Responds after the latency given by the request, failing empty requests.
+    struct SlowService(ManualClock);
+    impl Service<(u64, &'static str)> for SlowService {
+        type Response = &'static str;
+        type Error = Error;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, (millis, req): (u64, &'static str)) -> Self::Future {
+            self.0.advance(Duration::from_millis(millis));
+            if req.is_empty() {
+                return ready(Err("empty request".into()));
+            }
+            ready(Ok(req))
+        }
+    }
+
+    struct MakeSlow(ManualClock);
+    impl Service<Endpoint> for MakeSlow {
+        type Response = SlowService;
+        type Error = Error;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: Endpoint) -> Self::Future {
+            ready(Ok(SlowService(self.0.clone())))
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let clock = ManualClock::new();
+        let registry = Registry::with_buckets(&[0.1, 1.0], clock.clone());
+        let make_stack = MakeStack::new::<Endpoint>(Stack::new(MakeSlow(clock)))
+            .push_metrics::<Endpoint, (u64, &'static str), _>(registry.clone());
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let calls = [
+            ("10.0.0.1:80", 50, "a"),
+            ("10.0.0.1:80", 500, ""),
+            ("10.0.0.1:80", 2000, "b"),
+            ("10.0.0.2:80", 100, "c"),
+        ];
+        let cx = &mut noop_context();
+        for (addr, millis, req) in calls {
+            let mut svc = assert_ready_ok!(pin!(make_svc.call(Endpoint { addr })).poll(cx));
+            let res = assert_ready!(pin!(svc.call((millis, req))).poll(cx));
+            assert_eq!(res.is_ok(), !req.is_empty());
+        }
+
+        let expected = r#"# HELP requests_total The number of requests.
+# TYPE requests_total counter
+requests_total{addr="10.0.0.1:80"} 3
+requests_total{addr="10.0.0.2:80"} 1
+# HELP request_errors_total The number of failed requests.
+# TYPE request_errors_total counter
+request_errors_total{addr="10.0.0.1:80"} 1
+request_errors_total{addr="10.0.0.2:80"} 0
+# HELP request_duration_seconds The latency of the requests.
+# TYPE request_duration_seconds histogram
+request_duration_seconds_bucket{addr="10.0.0.1:80",le="0.1"} 1
+request_duration_seconds_bucket{addr="10.0.0.1:80",le="1"} 2
+request_duration_seconds_bucket{addr="10.0.0.1:80",le="+Inf"} 3
+request_duration_seconds_sum{addr="10.0.0.1:80"} 2.55
+request_duration_seconds_count{addr="10.0.0.1:80"} 3
+request_duration_seconds_bucket{addr="10.0.0.2:80",le="0.1"} 1
+request_duration_seconds_bucket{addr="10.0.0.2:80",le="1"} 1
+request_duration_seconds_bucket{addr="10.0.0.2:80",le="+Inf"} 1
+request_duration_seconds_sum{addr="10.0.0.2:80"} 0.1
+request_duration_seconds_count{addr="10.0.0.2:80"} 1
+"#;
+        assert_eq!(registry.render(), expected);
+    }
+
+    #[test]
+    fn test_escape_labels() {
+        let labels = Labels::new()
+            .with("path", "C:\\tmp")
+            .with("quote", "say \"hi\"\n");
+        let mut out = String::new();
+        labels.write(&mut out, Some(("le", "1")));
+        assert_eq!(out, r#"{path="C:\\tmp",quote="say \"hi\"\n",le="1"}"#);
+    }
+
+    #[test]
+    #[should_panic(expected = "latency buckets must be strictly increasing")]
+    fn test_unsorted_buckets() {
+        Registry::with_buckets(&[1.0, 0.1], SystemClock);
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{Error, ServiceFor};
+use pipeline_new_service::{IntoMakeService, NewService, NewServiceStack};
+use tower::{layer::layer_fn, Service};
+
+use crate::MakeStack;
+
+/// Turns a `tower::MakeService` into a `NewService`.
+///
+/// Each built service is a `LazyService` holding its own clone of `M`.
+///
+/// `M`: a thing that makes services
+#[derive(Clone, Debug)]
+pub struct IntoNewService<M>(M);
+impl<M> IntoNewService<M> {
+    pub fn new(inner: M) -> Self {
+        Self(inner)
+    }
+
+    pub fn into_inner(self) -> M {
+        self.0
+    }
+}
+impl<M, Tgt> NewService<Tgt> for IntoNewService<M>
+where
+    M: Service<Tgt> + Clone,
+{
+    type Service = LazyService<M, Tgt>;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        LazyService::new(self.0.clone(), target)
+    }
+}
+
+/// A service that is made on its first `poll_ready`.
+///
+/// Once made, readiness is delegated to the made service. If the make fails, every later `poll_ready` fails with a `MakeError`.
+pub struct LazyService<M, Tgt>
+where
+    M: Service<Tgt>,
+{
+    state: LazyState<M, Tgt>,
+}
+enum LazyState<M, Tgt>
+where
+    M: Service<Tgt>,
+{
+    Idle { make: M, target: Option<Tgt> },
+    Making(Pin<Box<M::Future>>),
+    Ready(M::Response),
+    Failed(MakeError),
+}
+impl<M, Tgt> LazyService<M, Tgt>
+where
+    M: Service<Tgt>,
+{
+    pub fn new(make: M, target: Tgt) -> Self {
+        Self {
+            state: LazyState::Idle {
+                make,
+                target: Some(target),
+            },
+        }
+    }
+}
+impl<M, Tgt, Req> Service<Req> for LazyService<M, Tgt>
+where
+    M: Service<Tgt>,
+    M::Error: Into<Error>,
+    M::Response: Service<Req>,
+    <M::Response as Service<Req>>::Error: Into<Error>,
+{
+    type Response = <M::Response as Service<Req>>::Response;
+    type Error = Error;
+    type Future = LazyFuture<<M::Response as Service<Req>>::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        loop {
+            self.state = match &mut self.state {
+                LazyState::Idle { make, target } => match make.poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {
+                        let target = target.take().expect("target is taken once");
+                        LazyState::Making(Box::pin(make.call(target)))
+                    }
+                    Poll::Ready(Err(e)) => LazyState::Failed(MakeError::new(e.into())),
+                    Poll::Pending => return Poll::Pending,
+                },
+                LazyState::Making(future) => match future.as_mut().poll(cx) {
+                    Poll::Ready(Ok(service)) => LazyState::Ready(service),
+                    Poll::Ready(Err(e)) => LazyState::Failed(MakeError::new(e.into())),
+                    Poll::Pending => return Poll::Pending,
+                },
+                LazyState::Ready(service) => {
+                    return service.poll_ready(cx).map_err(Into::into);
+                }
+                LazyState::Failed(e) => return Poll::Ready(Err(e.clone().into())),
+            };
+        }
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let LazyState::Ready(service) = &mut self.state else {
+            panic!("called before the service is ready");
+        };
+        LazyFuture {
+            future: service.call(req),
+        }
+    }
+}
+impl<M, Tgt> fmt::Debug for LazyService<M, Tgt>
+where
+    M: Service<Tgt>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match &self.state {
+            LazyState::Idle { .. } => "Idle",
+            LazyState::Making(_) => "Making",
+            LazyState::Ready(_) => "Ready",
+            LazyState::Failed(_) => "Failed",
+        };
+        f.debug_struct("LazyService")
+            .field("state", &state)
+            .finish()
+    }
+}
+
+pin_project! {
+    pub struct LazyFuture<F> {
+        #[pin]
+        future: F,
+    }
+}
+impl<F, T, E> Future for LazyFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().future.poll(cx).map_err(Into::into)
+    }
+}
+
+/// Making a service failed.
+///
+/// Clones share the same source error.
+#[derive(Clone, Debug)]
+pub struct MakeError(Arc<Error>);
+impl MakeError {
+    pub(crate) fn new(source: Error) -> Self {
+        Self(Arc::new(source))
+    }
+}
+impl fmt::Display for MakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to make service: {}", self.0)
+    }
+}
+impl std::error::Error for MakeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&**self.0)
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Turn the stack into a stack of a `NewService`.
+    ///
+    /// `Tgt`: the target type of the stack
+    ///
+    /// `Req`: the request type of the stack
+    pub fn into_new_service<Tgt, Req>(self) -> NewServiceStack<IntoNewService<M>>
+    where
+        IntoNewService<M>: NewService<Tgt>,
+        <IntoNewService<M> as NewService<Tgt>>::Service: Service<Req>,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("IntoNewService", layer_fn(IntoNewService::new));
+        NewServiceStack::new(stack).check_new_service::<Tgt, Req>()
+    }
+}
+
+impl<N> MakeStack<IntoMakeService<N>> {
+    /// Turn a stack of a `NewService` into a stack of a `tower::MakeService`.
+    ///
+    /// The make service is always ready and its futures resolve immediately.
+    ///
+    /// `Tgt`: the target type of the stack
+    ///
+    /// `Req`: the request type of the stack
+    pub fn from_new_service<Tgt, Req>(stack: NewServiceStack<N>) -> Self
+    where
+        N: NewService<Tgt>,
+        N::Service: ServiceFor<Req>,
+    {
+        MakeStack::new::<Tgt>(stack.into_make_service::<Tgt>()).check_make::<Tgt, Req>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        error::Error as _,
+        future::{ready, Ready},
+        pin::pin,
+        rc::Rc,
+    };
+
+    use pipeline_base::{Describe, Stack};
+    use pipeline_test::{
+        assert_pending, assert_ready_err, assert_ready_ok, noop_context, EchoService,
+    };
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Refused;
+    impl fmt::Display for Refused {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "refused")
+        }
+    }
+    impl std::error::Error for Refused {}
+
+    /// Makes `EchoService` once it is open and counts the services made.
+    ///
+    /// Targets starting with "bad" are refused.
+    #[derive(Clone)]
+    struct MakeEcho {
+        open: Rc<Cell<bool>>,
+        made: Rc<Cell<usize>>,
+    }
+    impl Service<String> for MakeEcho {
+        type Response = EchoService;
+        type Error = Refused;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.open.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+        fn call(&mut self, target: String) -> Self::Future {
+            self.made.set(self.made.get() + 1);
+            if target.starts_with("bad") {
+                return ready(Err(Refused));
+            }
+            ready(Ok(EchoService))
+        }
+    }
+
+    #[test]
+    fn test_into_new_service() {
+        let open = Rc::new(Cell::new(false));
+        let made = Rc::new(Cell::new(0));
+        let make_echo = MakeEcho {
+            open: open.clone(),
+            made: made.clone(),
+        };
+        let new_stack = MakeStack::new::<String>(Stack::new(make_echo))
+            .into_new_service::<String, String>()
+            .check_new_clone::<String>();
+        let new_svc = new_stack.into_inner().into_inner();
+
+        // Building the service does not make it.
+        let mut svc = new_svc.new_service("localhost".to_string());
+        assert_eq!(made.get(), 0);
+
+        // The service is not ready until the make service is.
+        let cx = &mut noop_context();
+        assert_pending!(Service::<String>::poll_ready(&mut svc, cx));
+        assert_eq!(made.get(), 0);
+
+        open.set(true);
+        assert_ready_ok!(Service::<String>::poll_ready(&mut svc, cx));
+        assert_eq!(made.get(), 1);
+
+        // The made service is reused.
+        for _ in 0..2 {
+            assert_ready_ok!(Service::<String>::poll_ready(&mut svc, cx));
+            let resp = assert_ready_ok!(pin!(svc.call("hello".to_string())).poll(cx));
+            assert_eq!(resp, "hello");
+        }
+        assert_eq!(made.get(), 1);
+
+        // Make errors surface through the built service.
+        let mut svc = new_svc.new_service("bad".to_string());
+        for _ in 0..2 {
+            let e = assert_ready_err!(Service::<String>::poll_ready(&mut svc, cx));
+            let e = e.downcast_ref::<MakeError>().unwrap();
+            assert!(e.source().unwrap().is::<Refused>());
+        }
+        assert_eq!(made.get(), 2);
+    }
+
+    /// Builds `EchoService` for any target.
+    struct NewEcho;
+    impl NewService<String> for NewEcho {
+        type Service = EchoService;
+        fn new_service(&self, _: String) -> Self::Service {
+            EchoService
+        }
+    }
+
+    #[test]
+    fn test_from_new_service() {
+        let new_stack = NewServiceStack::new(Stack::described(NewEcho));
+        let make_stack = MakeStack::from_new_service::<String, String>(new_stack);
+        assert_eq!(
+            make_stack.describe().to_string(),
+            "NewEcho\nIntoMakeService\n"
+        );
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(tower::MakeService::<String, String>::poll_ready(
+            &mut make_svc,
+            cx
+        ));
+        let mut svc = assert_ready_ok!(pin!(make_svc.call("localhost".to_string())).poll(cx));
+        let resp = assert_ready_ok!(pin!(svc.call("hello".to_string())).poll(cx));
+        assert_eq!(resp, "hello");
+    }
+}
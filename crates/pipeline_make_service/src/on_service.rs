@@ -4,6 +4,7 @@ use std::{
     task::{Context, Poll},
 };
 
+use pin_project_lite::pin_project;
 use tower::{Layer, Service};
 
 use crate::MakeStack;
@@ -16,24 +17,48 @@ pub struct OnService<L, M> {
 }
 impl<L, M, Tgt> Service<Tgt> for OnService<L, M>
 where
-    L: Layer<M::Response> + Clone + 'static,
+    L: Layer<M::Response> + Clone,
     M: Service<Tgt>,
-    M::Future: 'static,
 {
     type Response = L::Service;
     type Error = M::Error;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Future = OnServiceFuture<M::Future, L>;
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
     fn call(&mut self, req: Tgt) -> Self::Future {
-        let fut = self.inner.call(req);
-        let layer = self.layer.clone();
-        let next = async move {
-            let svc = fut.await?;
-            Ok(layer.layer(svc))
+        OnServiceFuture {
+            future: self.inner.call(req),
+            layer: Some(self.layer.clone()),
+        }
+    }
+}
+
+pin_project! {
+    /// Applies the layer to the service once it is made.
+    ///
+    /// `Send` if both the make future and the layer are `Send`.
+    pub struct OnServiceFuture<F, L> {
+        #[pin]
+        future: F,
+        layer: Option<L>,
+    }
+}
+impl<F, L, S, E> Future for OnServiceFuture<F, L>
+where
+    F: Future<Output = Result<S, E>>,
+    L: Layer<S>,
+{
+    type Output = Result<L::Service, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let svc = match this.future.poll(cx) {
+            Poll::Ready(Ok(svc)) => svc,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
         };
-        Box::pin(next)
+        let layer = this.layer.take().expect("polled after completion");
+        Poll::Ready(Ok(layer.layer(svc)))
     }
 }
 
@@ -63,10 +88,9 @@ impl<M> MakeStack<M> {
     /// The target metadata is passed to the inner service.
     pub fn push_on_service<Tgt, Req, L>(self, layer: L) -> MakeStack<OnService<L, M>>
     where
-        L: Layer<M::Response> + Clone + 'static,
+        L: Layer<M::Response> + Clone,
         L::Service: Service<Req>,
         M: Service<Tgt>,
-        M::Future: 'static,
     {
         let on_service_layer = OnServiceLayer::new(layer);
         self.push::<Tgt, Req, _>(on_service_layer)
@@ -77,91 +101,21 @@ impl<M> MakeStack<M> {
 mod tests {
     use std::{
         convert::Infallible,
-        future::{ready, Future, Ready},
-        pin::Pin,
-        task::{Context, Poll},
+        future::{ready, Ready},
+        pin::pin,
     };
 
-    use futures::pin_mut;
     use pipeline_base::Stack;
-    use tower::Service;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService, TraceBody, TraceService};
+    use tower::{layer::layer_fn, Service, ServiceExt};
 
     use super::*;
 
-    trait Trace {
-        fn history_mut(&mut self) -> &mut Vec<String>;
-    }
-    struct TraceBody {
-        history: Vec<String>,
-    }
-    impl Trace for TraceBody {
-        fn history_mut(&mut self) -> &mut Vec<String> {
-            &mut self.history
-        }
-    }
-
-    struct TraceService<S> {
-        inner: S,
-        tgt_mark: String,
-        req_mark: String,
-        resp_mark: String,
-    }
-    impl<S, Req> Service<Req> for TraceService<S>
-    where
-        Req: Trace,
-        S: Service<Req, Response = Req>,
-        S::Response: 'static,
-        S::Future: 'static,
-    {
-        type Response = S::Response;
-        type Error = S::Error;
-        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
-        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-            self.inner.poll_ready(cx)
-        }
-        fn call(&mut self, mut req: Req) -> Self::Future {
-            req.history_mut().push(self.tgt_mark.clone());
-            req.history_mut().push(self.req_mark.clone());
-            let fut = self.inner.call(req);
-            let tgt_mark = self.tgt_mark.clone();
-            let resp_mark = self.resp_mark.clone();
-            let next = async move {
-                let mut resp = fut.await?;
-                resp.history_mut().push(tgt_mark);
-                resp.history_mut().push(resp_mark);
-                Ok(resp)
-            };
-            let next = Box::pin(next);
-            next
-        }
-    }
-
-    struct EchoService;
-    impl<Req> Service<Req> for EchoService {
-        type Response = Req;
-        type Error = Box<Infallible>;
-        type Future = Ready<Result<Self::Response, Self::Error>>;
-        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
-        }
-        fn call(&mut self, req: Req) -> Self::Future {
-            ready(Ok(req))
-        }
-    }
-
-    #[derive(Debug, Clone)]
-    struct EchoLayer;
-    impl<S> Layer<S> for EchoLayer {
-        type Service = EchoService;
-        fn layer(&self, _: S) -> Self::Service {
-            EchoService
-        }
-    }
-
+    /// Wraps every made service in a `TraceService` whose marks start with the target.
     struct MakeTrace<M> {
         inner: M,
-        req_mark: String,
-        resp_mark: String,
+        req_mark: &'static str,
+        resp_mark: &'static str,
     }
     impl<M> Service<String> for MakeTrace<M>
     where
@@ -174,34 +128,25 @@ mod tests {
         fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
             self.inner.poll_ready(cx)
         }
-        fn call(&mut self, req: String) -> Self::Future {
-            let fut = self.inner.call(req.clone());
-            let req_mark = self.req_mark.clone();
-            let resp_mark = self.resp_mark.clone();
-            let next = async move {
-                let svc = fut.await?;
-                Ok(TraceService {
-                    inner: svc,
-                    tgt_mark: req,
-                    req_mark,
-                    resp_mark,
-                })
-            };
-            Box::pin(next)
+        fn call(&mut self, target: String) -> Self::Future {
+            let fut = self.inner.call(target.clone());
+            let req_mark = format!("{target} {}", self.req_mark);
+            let resp_mark = format!("{target} {}", self.resp_mark);
+            Box::pin(async move { Ok(TraceService::new(fut.await?, req_mark, resp_mark)) })
         }
     }
 
     struct MakeTraceLayer {
-        req_mark: String,
-        resp_mark: String,
+        req_mark: &'static str,
+        resp_mark: &'static str,
     }
     impl<M> Layer<M> for MakeTraceLayer {
         type Service = MakeTrace<M>;
         fn layer(&self, inner: M) -> Self::Service {
             MakeTrace {
                 inner,
-                req_mark: self.req_mark.clone(),
-                resp_mark: self.resp_mark.clone(),
+                req_mark: self.req_mark,
+                resp_mark: self.resp_mark,
             }
         }
     }
@@ -223,65 +168,51 @@ mod tests {
     fn test_make() {
         let stack = Stack::new(VoidService);
         let make_stack = MakeStack::new::<String>(stack)
-            .push_on_service::<String, TraceBody, _>(EchoLayer)
+            .push_on_service::<String, TraceBody, _>(layer_fn(|_| EchoService))
             .push::<String, TraceBody, _>(MakeTraceLayer {
-                req_mark: "req_1".to_string(),
-                resp_mark: "resp_1".to_string(),
+                req_mark: "req_1",
+                resp_mark: "resp_1",
             })
             .push::<String, TraceBody, _>(MakeTraceLayer {
-                req_mark: "req_2".to_string(),
-                resp_mark: "resp_2".to_string(),
+                req_mark: "req_2",
+                resp_mark: "resp_2",
             });
         let mut make_svc = make_stack.into_inner().into_inner();
 
-        let target = "target".to_string();
-
-        // Poll the make pipeline.
-        let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-        let poll_ready = tower::MakeService::<String, TraceBody>::poll_ready(&mut make_svc, cx);
-        let Poll::Ready(Ok(())) = poll_ready else {
-            panic!("poll_ready failed");
-        };
-
-        // Call the make pipeline.
-        let fut = make_svc.call(target.clone());
-        pin_mut!(fut);
-        let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-        let Poll::Ready(Ok(mut svc)) = fut.as_mut().poll(cx) else {
-            panic!("call failed");
-        };
-
-        let req = TraceBody { history: vec![] };
-
-        // Poll the service.
-        let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-        let poll_ready =
-            <TraceService<TraceService<EchoService>> as Service<TraceBody>>::poll_ready(
-                &mut svc, cx,
-            );
-        let Poll::Ready(Ok(())) = poll_ready else {
-            panic!("poll_ready failed");
-        };
-
-        // Call the service.
-        let fut = svc.call(req);
-        pin_mut!(fut);
-        let cx = &mut Context::from_waker(futures::task::noop_waker_ref());
-        let Poll::Ready(Ok(resp)) = fut.as_mut().poll(cx) else {
-            panic!("call failed");
-        };
+        let cx = &mut noop_context();
+        assert_ready_ok!(tower::MakeService::<String, TraceBody>::poll_ready(
+            &mut make_svc,
+            cx
+        ));
+        let mut svc = assert_ready_ok!(pin!(make_svc.call("target".to_string())).poll(cx));
+
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(
+            resp.history,
+            [
+                "target req_2",
+                "target req_1",
+                "target resp_1",
+                "target resp_2"
+            ]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_make_send() {
+        let stack = Stack::new(VoidService);
+        let make_stack = MakeStack::new::<String>(stack)
+            .push_on_service::<String, String, _>(layer_fn(|_| EchoService));
+        let mut make_svc = make_stack.into_inner().into_inner();
 
-        // Check the response.
-        let expected = vec![
-            target.clone(),
-            "req_2".to_string(),
-            target.clone(),
-            "req_1".to_string(),
-            target.clone(),
-            "resp_1".to_string(),
-            target.clone(),
-            "resp_2".to_string(),
-        ];
-        assert_eq!(resp.history, expected);
+        // Use the make pipeline on another thread.
+        let handle = tokio::spawn(async move {
+            let make_svc = ServiceExt::<String>::ready(&mut make_svc).await.unwrap();
+            let svc = make_svc.call("target".to_string()).await.unwrap();
+            svc.oneshot("hello".to_string()).await
+        });
+        let resp = handle.await.unwrap().unwrap();
+        assert_eq!(resp, "hello");
     }
 }
@@ -0,0 +1,495 @@
+use std::{
+    fmt,
+    future::{poll_fn, Future},
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{Error, FailFastError, Param};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::PollSender;
+use tower::{Layer, Service};
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// The queue parameters of a target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueConfig {
+    /// The number of requests that can wait for the service; 0 is treated as 1
+    pub capacity: usize,
+    /// How long the service can stay unready before waiting requests are failed
+    pub failfast_timeout: Duration,
+}
+
+/// A worker driving a made service.
+pub type BoxWorker = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// Spawns the workers of the queues.
+pub trait Executor {
+    fn execute(&self, worker: BoxWorker);
+}
+impl<F> Executor for F
+where
+    F: Fn(BoxWorker),
+{
+    fn execute(&self, worker: BoxWorker) {
+        self(worker)
+    }
+}
+
+/// Spawns the workers onto the current tokio runtime.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioExecutor;
+impl Executor for TokioExecutor {
+    fn execute(&self, worker: BoxWorker) {
+        tokio::spawn(worker);
+    }
+}
+
+/// Spawns every made service onto a worker and returns a cloneable `Queue` handle to it.
+///
+/// `M`: a thing that makes services
+pub struct MakeQueue<M, E, Req> {
+    inner: M,
+    executor: E,
+    _req: PhantomData<fn(Req)>,
+}
+impl<M: Clone, E: Clone, Req> Clone for MakeQueue<M, E, Req> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            executor: self.executor.clone(),
+            _req: PhantomData,
+        }
+    }
+}
+impl<M: fmt::Debug, E: fmt::Debug, Req> fmt::Debug for MakeQueue<M, E, Req> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MakeQueue")
+            .field("inner", &self.inner)
+            .field("executor", &self.executor)
+            .finish()
+    }
+}
+impl<M, E, Req, Tgt> Service<Tgt> for MakeQueue<M, E, Req>
+where
+    Tgt: Param<QueueConfig>,
+    M: Service<Tgt>,
+    M::Response: Service<Req> + Send + 'static,
+    <M::Response as Service<Req>>::Future: Send + 'static,
+    <M::Response as Service<Req>>::Error: Into<Error>,
+    E: Executor + Clone,
+    Req: Send + 'static,
+{
+    type Response = Queue<Req, <M::Response as Service<Req>>::Future>;
+    type Error = M::Error;
+    type Future = MakeQueueFuture<M::Future, E, Req>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let config = target.param();
+        MakeQueueFuture {
+            future: self.inner.call(target),
+            executor: self.executor.clone(),
+            config,
+            _req: PhantomData,
+        }
+    }
+}
+
+pin_project! {
+    pub struct MakeQueueFuture<F, E, Req> {
+        #[pin]
+        future: F,
+        executor: E,
+        config: QueueConfig,
+        _req: PhantomData<fn(Req)>,
+    }
+}
+impl<F, E, Req, S, Err> Future for MakeQueueFuture<F, E, Req>
+where
+    F: Future<Output = Result<S, Err>>,
+    S: Service<Req> + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<Error>,
+    E: Executor,
+    Req: Send + 'static,
+{
+    type Output = Result<Queue<Req, S::Future>, Err>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let service = match this.future.poll(cx) {
+            Poll::Ready(Ok(service)) => service,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        let (tx, rx) = mpsc::channel(this.config.capacity.max(1));
+        let worker = run(service, rx, this.config.failfast_timeout);
+        this.executor.execute(Box::pin(worker));
+        Poll::Ready(Ok(Queue {
+            tx: PollSender::new(tx),
+        }))
+    }
+}
+
+/// A request waiting for the service and the channel to send its response future back.
+struct Message<Req, F> {
+    req: Req,
+    tx: oneshot::Sender<Result<F, Error>>,
+}
+
+/// Drive `service` with the requests from `rx` until all `Queue` handles are dropped or the service fails.
+async fn run<S, Req>(
+    mut service: S,
+    mut rx: mpsc::Receiver<Message<Req, S::Future>>,
+    failfast_timeout: Duration,
+) where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+{
+    while let Some(msg) = rx.recv().await {
+        let ready = poll_fn(|cx| service.poll_ready(cx).map_err(Into::into));
+        match tokio::time::timeout(failfast_timeout, ready).await {
+            Ok(Ok(())) => {
+                let _ = msg.tx.send(Ok(service.call(msg.req)));
+            }
+            Ok(Err(e)) => {
+                let _ = msg.tx.send(Err(e));
+                return;
+            }
+            Err(_) => {
+                let _ = msg.tx.send(Err(FailFastError.into()));
+
+                // Fail the waiting requests until the service becomes ready again.
+                loop {
+                    // Check the readiness of the service before taking another request.
+                    let event = poll_fn(|cx| match service.poll_ready(cx) {
+                        Poll::Ready(ready) => Poll::Ready(Ok(ready)),
+                        Poll::Pending => rx.poll_recv(cx).map(Err),
+                    })
+                    .await;
+                    match event {
+                        Ok(Ok(())) => break,
+                        Ok(Err(_)) => return,
+                        Err(Some(msg)) => {
+                            let _ = msg.tx.send(Err(FailFastError.into()));
+                        }
+                        Err(None) => return,
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A cloneable handle to a service driven by a worker.
+pub struct Queue<Req, F> {
+    tx: PollSender<Message<Req, F>>,
+}
+impl<Req, F> Clone for Queue<Req, F> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+impl<Req, F> fmt::Debug for Queue<Req, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Queue").finish()
+    }
+}
+impl<Req, F, T, E> Service<Req> for Queue<Req, F>
+where
+    F: Future<Output = Result<T, E>> + Send + 'static,
+    E: Into<Error>,
+    Req: Send + 'static,
+{
+    type Response = T;
+    type Error = Error;
+    type Future = QueueFuture<F>;
+    /// Ready once there is room in the queue.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.tx
+            .poll_reserve(cx)
+            .map_err(|_| QueueClosedError.into())
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let (tx, rx) = oneshot::channel();
+        match self.tx.send_item(Message { req, tx }) {
+            Ok(()) => QueueFuture::Waiting { rx },
+            Err(_) => QueueFuture::Failed {
+                error: Some(QueueClosedError.into()),
+            },
+        }
+    }
+}
+
+pin_project! {
+    #[project = QueueFutureProj]
+    pub enum QueueFuture<F> {
+        Waiting { rx: oneshot::Receiver<Result<F, Error>> },
+        Calling { #[pin] future: F },
+        Failed { error: Option<Error> },
+    }
+}
+impl<F, T, E> Future for QueueFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+{
+    type Output = Result<T, Error>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let future = match self.as_mut().project() {
+                QueueFutureProj::Waiting { rx } => match Pin::new(rx).poll(cx) {
+                    Poll::Ready(Ok(Ok(future))) => future,
+                    Poll::Ready(Ok(Err(e))) => return Poll::Ready(Err(e)),
+                    Poll::Ready(Err(_)) => return Poll::Ready(Err(QueueClosedError.into())),
+                    Poll::Pending => return Poll::Pending,
+                },
+                QueueFutureProj::Calling { future } => {
+                    return future.poll(cx).map_err(Into::into);
+                }
+                QueueFutureProj::Failed { error } => {
+                    let error = error.take().expect("polled after completion");
+                    return Poll::Ready(Err(error));
+                }
+            };
+            self.set(QueueFuture::Calling { future });
+        }
+    }
+}
+
+/// The worker of the queue is gone.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueueClosedError;
+impl fmt::Display for QueueClosedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "queue closed")
+    }
+}
+impl std::error::Error for QueueClosedError {}
+
+pub struct MakeQueueLayer<E, Req> {
+    executor: E,
+    _req: PhantomData<fn(Req)>,
+}
+impl<E, Req> MakeQueueLayer<E, Req> {
+    pub fn new(executor: E) -> Self {
+        Self {
+            executor,
+            _req: PhantomData,
+        }
+    }
+}
+impl<E: Clone, Req> Clone for MakeQueueLayer<E, Req> {
+    fn clone(&self) -> Self {
+        Self::new(self.executor.clone())
+    }
+}
+impl<E: Clone, M, Req> Layer<M> for MakeQueueLayer<E, Req> {
+    type Service = MakeQueue<M, E, Req>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeQueue {
+            inner,
+            executor: self.executor.clone(),
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Spawn every made service onto a worker with `executor` and make cloneable handles to it instead.
+    ///
+    /// The queue is configured by the `QueueConfig` parameter of the target.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_queue<Tgt, Req, E>(self, executor: E) -> MakeStack<MakeQueue<M, E, Req>>
+    where
+        E: Clone,
+        MakeQueue<M, E, Req>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeQueueLayer::new(executor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        task::Waker,
+    };
+
+    use pipeline_base::Stack;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    struct Endpoint {
+        addr: &'static str,
+        capacity: usize,
+    }
+    impl Param<QueueConfig> for Endpoint {
+        fn param(&self) -> QueueConfig {
+            QueueConfig {
+                capacity: self.capacity,
+                failfast_timeout: Duration::from_secs(1),
+            }
+        }
+    }
+
+    /// Whether the `GatedService`s are ready.
+    #[derive(Default)]
+    struct Open {
+        open: AtomicBool,
+        waker: Mutex<Option<Waker>>,
+    }
+    impl Open {
+        fn set(&self, open: bool) {
+            self.open.store(open, Ordering::SeqCst);
+            if let Some(waker) = self.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Responds with the address it is made for and the request once it is open.
+    ///
+    /// Not `Clone`, so it can only be shared through a queue.
+    struct GatedService {
+        addr: &'static str,
+        open: Arc<Open>,
+    }
+    impl Service<String> for GatedService {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.open.open.load(Ordering::SeqCst) {
+                return Poll::Ready(Ok(()));
+            }
+            *self.open.waker.lock().unwrap() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+        fn call(&mut self, req: String) -> Self::Future {
+            ready(Ok(format!("{} {}", self.addr, req)))
+        }
+    }
+
+    struct MakeGated {
+        open: Arc<Open>,
+    }
+    impl Service<Endpoint> for MakeGated {
+        type Response = GatedService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, target: Endpoint) -> Self::Future {
+            ready(Ok(GatedService {
+                addr: target.addr,
+                open: self.open.clone(),
+            }))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_queue() {
+        let open = Arc::new(Open::default());
+        open.set(true);
+        let make_stack = MakeStack::new::<Endpoint>(Stack::new(MakeGated { open: open.clone() }))
+            .push_queue::<Endpoint, String, _>(TokioExecutor);
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let queue = ServiceExt::<Endpoint>::oneshot(
+            &mut make_svc,
+            Endpoint {
+                addr: "10.0.0.1:80",
+                capacity: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+        // The handles share the same service.
+        for req in ["a", "b"] {
+            let resp = queue.clone().oneshot(req.to_string()).await.unwrap();
+            assert_eq!(resp, format!("10.0.0.1:80 {req}"));
+        }
+
+        // Requests fail fast while the service stays unready.
+        open.set(false);
+        let e = queue.clone().oneshot("c".to_string()).await.unwrap_err();
+        assert!(e.is::<FailFastError>());
+        let e = queue.clone().oneshot("d".to_string()).await.unwrap_err();
+        assert!(e.is::<FailFastError>());
+
+        // The service recovers once it is ready again.
+        open.set(true);
+        tokio::task::yield_now().await;
+        let resp = queue.clone().oneshot("e".to_string()).await.unwrap();
+        assert_eq!(resp, "10.0.0.1:80 e");
+    }
+
+    #[tokio::test]
+    async fn test_local_executor() {
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let executor = |worker: BoxWorker| {
+                    tokio::task::spawn_local(worker);
+                };
+                let open = Arc::new(Open::default());
+                open.set(true);
+                let make_stack = MakeStack::new::<Endpoint>(Stack::new(MakeGated { open }))
+                    .push_queue::<Endpoint, String, _>(executor);
+                let mut make_svc = make_stack.into_inner().into_inner();
+
+                let queue = ServiceExt::<Endpoint>::oneshot(
+                    &mut make_svc,
+                    Endpoint {
+                        addr: "10.0.0.2:80",
+                        capacity: 2,
+                    },
+                )
+                .await
+                .unwrap();
+                let resp = queue.oneshot("hello".to_string()).await.unwrap();
+                assert_eq!(resp, "10.0.0.2:80 hello");
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_zero_capacity() {
+        let open = Arc::new(Open::default());
+        open.set(true);
+        let make_stack = MakeStack::new::<Endpoint>(Stack::new(MakeGated { open }))
+            .push_queue::<Endpoint, String, _>(TokioExecutor);
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let queue = ServiceExt::<Endpoint>::oneshot(
+            &mut make_svc,
+            Endpoint {
+                addr: "10.0.0.3:80",
+                capacity: 0,
+            },
+        )
+        .await
+        .unwrap();
+        let resp = queue.oneshot("hello".to_string()).await.unwrap();
+        assert_eq!(resp, "10.0.0.3:80 hello");
+    }
+}
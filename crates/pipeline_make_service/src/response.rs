@@ -0,0 +1,262 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{
+    AndThen, AndThenLayer, MapResponse, MapResponseLayer, OnResponse, OnResponseLayer,
+};
+use tower::{Layer, Service};
+
+use crate::{MakeServiceFor, MakeStack, OnService};
+
+/// Converts the responses of every service made by `M` with a function that also sees the target.
+///
+/// `M`: a thing that makes services
+///
+/// `F`: a function from the target and the response of a made service to the new response
+#[derive(Clone, Debug)]
+pub struct MakeMapResponse<F, M> {
+    inner: M,
+    f: F,
+}
+impl<F, M, Tgt> Service<Tgt> for MakeMapResponse<F, M>
+where
+    F: Clone,
+    M: Service<Tgt>,
+    Tgt: Clone,
+{
+    type Response = TargetMapResponse<M::Response, F, Tgt>;
+    type Error = M::Error;
+    type Future = MakeMapResponseFuture<M::Future, F, Tgt>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let kept = target.clone();
+        MakeMapResponseFuture {
+            future: self.inner.call(target),
+            parts: Some((self.f.clone(), Arc::new(kept))),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MakeMapResponseFuture<Fut, F, Tgt> {
+        #[pin]
+        future: Fut,
+        parts: Option<(F, Arc<Tgt>)>,
+    }
+}
+impl<Fut, F, Tgt, S, E> Future for MakeMapResponseFuture<Fut, F, Tgt>
+where
+    Fut: Future<Output = Result<S, E>>,
+{
+    type Output = Result<TargetMapResponse<S, F, Tgt>, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let (f, target) = this.parts.take().expect("polled after completion");
+        Poll::Ready(res.map(|inner| TargetMapResponse { inner, f, target }))
+    }
+}
+
+/// A made service whose responses are converted with its target.
+#[derive(Debug)]
+pub struct TargetMapResponse<S, F, Tgt> {
+    inner: S,
+    f: F,
+    target: Arc<Tgt>,
+}
+impl<S: Clone, F: Clone, Tgt> Clone for TargetMapResponse<S, F, Tgt> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            f: self.f.clone(),
+            target: self.target.clone(),
+        }
+    }
+}
+impl<S, F, Tgt, Req, R> Service<Req> for TargetMapResponse<S, F, Tgt>
+where
+    S: Service<Req>,
+    F: FnOnce(&Tgt, S::Response) -> R + Clone,
+{
+    type Response = R;
+    type Error = S::Error;
+    type Future = TargetMapResponseFuture<S::Future, F, Tgt>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        TargetMapResponseFuture {
+            future: self.inner.call(req),
+            parts: Some((self.f.clone(), self.target.clone())),
+        }
+    }
+}
+
+pin_project! {
+    pub struct TargetMapResponseFuture<Fut, F, Tgt> {
+        #[pin]
+        future: Fut,
+        parts: Option<(F, Arc<Tgt>)>,
+    }
+}
+impl<Fut, F, Tgt, T, E, R> Future for TargetMapResponseFuture<Fut, F, Tgt>
+where
+    Fut: Future<Output = Result<T, E>>,
+    F: FnOnce(&Tgt, T) -> R,
+{
+    type Output = Result<R, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let (f, target) = this.parts.take().expect("polled after completion");
+        Poll::Ready(res.map(|resp| f(&target, resp)))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MakeMapResponseLayer<F>(F);
+impl<F> MakeMapResponseLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<F: Clone, M> Layer<M> for MakeMapResponseLayer<F> {
+    type Service = MakeMapResponse<F, M>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeMapResponse {
+            inner,
+            f: self.0.clone(),
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Convert the responses of the made services.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_map_response<Tgt, Req, F>(
+        self,
+        f: F,
+    ) -> MakeStack<OnService<MapResponseLayer<F>, M>>
+    where
+        F: Clone,
+        M: Service<Tgt>,
+        MapResponse<M::Response, F>: Service<Req>,
+    {
+        self.push_on_service::<Tgt, Req, _>(MapResponseLayer::new(f))
+    }
+
+    /// Convert the responses of the made services asynchronously.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_and_then<Tgt, Req, F>(self, f: F) -> MakeStack<OnService<AndThenLayer<F>, M>>
+    where
+        F: Clone,
+        M: Service<Tgt>,
+        AndThen<M::Response, F>: Service<Req>,
+    {
+        self.push_on_service::<Tgt, Req, _>(AndThenLayer::new(f))
+    }
+
+    /// Inspect or modify the responses of the made services in place.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_on_response<Tgt, Req, F>(self, f: F) -> MakeStack<OnService<OnResponseLayer<F>, M>>
+    where
+        F: Clone,
+        M: Service<Tgt>,
+        OnResponse<M::Response, F>: Service<Req>,
+    {
+        self.push_on_service::<Tgt, Req, _>(OnResponseLayer::new(f))
+    }
+
+    /// Convert the responses of the made services with the target they were made for.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_map_response_with_target<Tgt, Req, F>(
+        self,
+        f: F,
+    ) -> MakeStack<MakeMapResponse<F, M>>
+    where
+        F: Clone,
+        MakeMapResponse<F, M>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeMapResponseLayer::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+    };
+
+    use futures::pin_mut;
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+
+    use super::*;
+
+    /// Makes `EchoService` for any target.
+    struct MakeEcho;
+    impl<Tgt> Service<Tgt> for MakeEcho {
+        type Response = EchoService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: Tgt) -> Self::Future {
+            ready(Ok(EchoService))
+        }
+    }
+
+    #[test]
+    fn test_response() {
+        let make_stack = MakeStack::new::<&str>(Stack::new(MakeEcho))
+            .push_map_response::<&str, String, _>(|resp: String| resp.to_uppercase())
+            .push_and_then::<&str, String, _>(|resp: String| ready(Ok(format!("{resp}!"))))
+            .push_on_response::<&str, String, _>(|resp: &mut String| resp.insert(0, '<'))
+            .push_map_response_with_target::<&str, String, _>(|addr: &&str, resp: String| {
+                format!("{addr} {resp}")
+            });
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        for addr in ["10.0.0.1:80", "10.0.0.2:80"] {
+            // Make the service.
+            let cx = &mut noop_context();
+            let fut = make_svc.call(addr);
+            pin_mut!(fut);
+            let mut svc = assert_ready_ok!(fut.as_mut().poll(cx));
+
+            // Call the service.
+            assert_ready_ok!(svc.poll_ready(cx));
+            let fut = svc.call("hello".to_string());
+            pin_mut!(fut);
+            let resp = assert_ready_ok!(fut.as_mut().poll(cx));
+            assert_eq!(resp, format!("{addr} <HELLO!"));
+        }
+    }
+}
@@ -0,0 +1,657 @@
+use std::{
+    convert::Infallible,
+    fmt,
+    future::{ready, Future, Ready},
+    hash::Hash,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Wake, Waker},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{Cache, Clock, Error, SystemClock};
+use tower::{Layer, Service};
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// Derives the routing key of a request from the target and the request.
+pub trait RecognizeRoute<Tgt, Req> {
+    type Key;
+
+    fn recognize(&self, target: &Tgt, req: &Req) -> Self::Key;
+}
+impl<F, Tgt, Req, K> RecognizeRoute<Tgt, Req> for F
+where
+    F: Fn(&Tgt, &Req) -> K,
+{
+    type Key = K;
+    fn recognize(&self, target: &Tgt, req: &Req) -> Self::Key {
+        self(target, req)
+    }
+}
+
+/// Makes a `Router` for each target.
+///
+/// `M`: a thing that makes services for routing keys
+#[derive(Debug)]
+pub struct MakeRouter<R, M, Req, C = SystemClock> {
+    recognize: R,
+    inner: M,
+    idle_timeout: Duration,
+    clock: C,
+    _req: PhantomData<fn(Req)>,
+}
+impl<R: Clone, M: Clone, Req, C: Clone> Clone for MakeRouter<R, M, Req, C> {
+    fn clone(&self) -> Self {
+        Self {
+            recognize: self.recognize.clone(),
+            inner: self.inner.clone(),
+            idle_timeout: self.idle_timeout,
+            clock: self.clock.clone(),
+            _req: PhantomData,
+        }
+    }
+}
+impl<R, M, Req, C, Tgt> Service<Tgt> for MakeRouter<R, M, Req, C>
+where
+    R: RecognizeRoute<Tgt, Req> + Clone,
+    R::Key: Hash + Eq,
+    M: Service<R::Key> + Clone,
+    C: Clock + Clone,
+{
+    type Response = Router<Tgt, R, M, R::Key, C>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let router = Router {
+            target,
+            recognize: self.recognize.clone(),
+            inner: self.inner.clone(),
+            routes: Cache::new(self.idle_timeout, self.clock.clone()),
+        };
+        ready(Ok(router))
+    }
+}
+
+/// Dispatches each request to the service made for its routing key.
+///
+/// The service for a key is made on the first request with that key and reused afterwards. A route that has not
+/// received a request for the idle timeout is dropped, so keys taken from requests cannot grow the router without
+/// bound. A route whose make or whose service fails is made again on the next request.
+///
+/// Readiness is deferred to the response future: `poll_ready` is always ready, and the future returned by `call` waits
+/// for its route's make service and then its route's service to be ready before calling it. A route that is not ready
+/// therefore does not hold back requests to other routes. Every request waiting on a route is woken when the route may
+/// have become ready. Bound the requests in flight above the router if the callers need backpressure.
+pub struct Router<Tgt, R, M, K, C = SystemClock>
+where
+    M: Service<K>,
+{
+    target: Tgt,
+    recognize: R,
+    inner: M,
+    routes: Cache<K, Arc<Mutex<Route<M, K>>>, C>,
+}
+impl<Tgt, R, M, K, C, Req> Service<Req> for Router<Tgt, R, M, K, C>
+where
+    R: RecognizeRoute<Tgt, Req, Key = K>,
+    K: Hash + Eq + Clone,
+    M: Service<K> + Clone,
+    C: Clock,
+    M::Error: Into<Error>,
+    M::Response: Service<Req>,
+    <M::Response as Service<Req>>::Error: Into<Error>,
+{
+    type Response = <M::Response as Service<Req>>::Response;
+    type Error = Error;
+    type Future = RouterFuture<M, K, Req>;
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let key = self.recognize.recognize(&self.target, &req);
+        let route = match self.routes.get(&key) {
+            Some(route) => route,
+            None => {
+                let route = Arc::new(Mutex::new(Route::new(self.inner.clone(), key.clone())));
+                self.routes.insert(key, route.clone());
+                route
+            }
+        };
+        RouterFuture::Routing {
+            route,
+            req: Some(req),
+        }
+    }
+}
+
+/// The service of a routing key, made on demand.
+pub struct Route<M, K>
+where
+    M: Service<K>,
+{
+    inner: M,
+    key: K,
+    state: RouteState<M::Future, M::Response>,
+    waiters: Arc<Waiters>,
+    /// Wakes all the `waiters`; passed to the inner services instead of the waker of a single request.
+    waker: Waker,
+}
+enum RouteState<F, S> {
+    Idle,
+    Making(Pin<Box<F>>),
+    Ready(S),
+}
+impl<M, K> Route<M, K>
+where
+    M: Service<K>,
+{
+    fn new(inner: M, key: K) -> Self {
+        let waiters = Arc::new(Waiters::default());
+        Self {
+            inner,
+            key,
+            state: RouteState::Idle,
+            waker: Waker::from(waiters.clone()),
+            waiters,
+        }
+    }
+
+    /// Make the service if needed, wait for it to be ready and call it with the request.
+    ///
+    /// A failed make or a failed service is made again on the next request.
+    fn poll_call<Req>(
+        &mut self,
+        cx: &mut Context<'_>,
+        req: &mut Option<Req>,
+    ) -> Poll<Result<<M::Response as Service<Req>>::Future, Error>>
+    where
+        K: Clone,
+        M::Error: Into<Error>,
+        M::Response: Service<Req>,
+        <M::Response as Service<Req>>::Error: Into<Error>,
+    {
+        self.waiters.register(cx.waker());
+        let poll = self.poll_call_inner(req);
+        if poll.is_ready() {
+            self.waiters.remove(cx.waker());
+        }
+        poll
+    }
+
+    fn poll_call_inner<Req>(
+        &mut self,
+        req: &mut Option<Req>,
+    ) -> Poll<Result<<M::Response as Service<Req>>::Future, Error>>
+    where
+        K: Clone,
+        M::Error: Into<Error>,
+        M::Response: Service<Req>,
+        <M::Response as Service<Req>>::Error: Into<Error>,
+    {
+        let cx = &mut Context::from_waker(&self.waker);
+        loop {
+            self.state = match &mut self.state {
+                RouteState::Idle => {
+                    match self.inner.poll_ready(cx) {
+                        Poll::Ready(Ok(())) => (),
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                    let future = self.inner.call(self.key.clone());
+                    RouteState::Making(Box::pin(future))
+                }
+                RouteState::Making(future) => match future.as_mut().poll(cx) {
+                    Poll::Ready(Ok(service)) => RouteState::Ready(service),
+                    Poll::Ready(Err(e)) => {
+                        self.state = RouteState::Idle;
+                        return Poll::Ready(Err(e.into()));
+                    }
+                    Poll::Pending => return Poll::Pending,
+                },
+                RouteState::Ready(service) => {
+                    return match service.poll_ready(cx) {
+                        Poll::Ready(Ok(())) => {
+                            let req = req.take().expect("polled after completion");
+                            Poll::Ready(Ok(service.call(req)))
+                        }
+                        Poll::Ready(Err(e)) => {
+                            self.state = RouteState::Idle;
+                            Poll::Ready(Err(e.into()))
+                        }
+                        Poll::Pending => Poll::Pending,
+                    };
+                }
+            };
+        }
+    }
+}
+
+/// The wakers of the requests waiting on a route.
+#[derive(Default)]
+struct Waiters(Mutex<Vec<Waker>>);
+impl Waiters {
+    fn register(&self, waker: &Waker) {
+        // A list of wakers is valid even if a holder of the lock panicked.
+        let mut wakers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    fn remove(&self, waker: &Waker) {
+        let mut wakers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        wakers.retain(|w| !w.will_wake(waker));
+    }
+}
+impl Wake for Waiters {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+    fn wake_by_ref(self: &Arc<Self>) {
+        let wakers = std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// A request to a route that panicked while being polled.
+#[derive(Debug)]
+pub struct RoutePoisoned(());
+impl fmt::Display for RoutePoisoned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a previous request to the route panicked")
+    }
+}
+impl std::error::Error for RoutePoisoned {}
+
+pin_project! {
+    #[project = RouterFutureProj]
+    pub enum RouterFuture<M, K, Req>
+    where
+        M: Service<K>,
+        M::Response: Service<Req>,
+    {
+        Routing {
+            route: Arc<Mutex<Route<M, K>>>,
+            req: Option<Req>,
+        },
+        Calling {
+            #[pin]
+            future: <M::Response as Service<Req>>::Future,
+        },
+    }
+}
+impl<M, K, Req> Future for RouterFuture<M, K, Req>
+where
+    K: Clone,
+    M: Service<K>,
+    M::Error: Into<Error>,
+    M::Response: Service<Req>,
+    <M::Response as Service<Req>>::Error: Into<Error>,
+{
+    type Output = Result<<M::Response as Service<Req>>::Response, Error>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let future = match self.as_mut().project() {
+                RouterFutureProj::Routing { route, req } => {
+                    let Ok(mut route) = route.lock() else {
+                        return Poll::Ready(Err(RoutePoisoned(()).into()));
+                    };
+                    match route.poll_call(cx, req) {
+                        Poll::Ready(Ok(future)) => future,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                RouterFutureProj::Calling { future } => {
+                    return future.poll(cx).map_err(Into::into);
+                }
+            };
+            self.set(RouterFuture::Calling { future });
+        }
+    }
+}
+
+pub struct MakeRouterLayer<R, Req, C = SystemClock> {
+    recognize: R,
+    idle_timeout: Duration,
+    clock: C,
+    _req: PhantomData<fn(Req)>,
+}
+impl<R, Req, C> MakeRouterLayer<R, Req, C> {
+    pub fn new(recognize: R, idle_timeout: Duration, clock: C) -> Self {
+        Self {
+            recognize,
+            idle_timeout,
+            clock,
+            _req: PhantomData,
+        }
+    }
+}
+impl<R: Clone, Req, C: Clone> Clone for MakeRouterLayer<R, Req, C> {
+    fn clone(&self) -> Self {
+        Self::new(
+            self.recognize.clone(),
+            self.idle_timeout,
+            self.clock.clone(),
+        )
+    }
+}
+impl<R: Clone, M, Req, C: Clone> Layer<M> for MakeRouterLayer<R, Req, C> {
+    type Service = MakeRouter<R, M, Req, C>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeRouter {
+            recognize: self.recognize.clone(),
+            inner,
+            idle_timeout: self.idle_timeout,
+            clock: self.clock.clone(),
+            _req: PhantomData,
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Route each request to a service made by the current stack for the request's routing key.
+    ///
+    /// A route is dropped after not receiving a request for `idle_timeout`.
+    ///
+    /// `recognize`: derives the routing key from the target and the request
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_router<Tgt, Req, R>(
+        self,
+        recognize: R,
+        idle_timeout: Duration,
+    ) -> MakeStack<MakeRouter<R, M, Req, SystemClock>>
+    where
+        R: Clone,
+        MakeRouter<R, M, Req, SystemClock>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push_router_with_clock::<Tgt, Req, R, _>(recognize, idle_timeout, SystemClock)
+    }
+
+    /// The same as `push_router` but reads the time from `clock`.
+    pub fn push_router_with_clock<Tgt, Req, R, C>(
+        self,
+        recognize: R,
+        idle_timeout: Duration,
+        clock: C,
+    ) -> MakeStack<MakeRouter<R, M, Req, C>>
+    where
+        R: Clone,
+        C: Clone,
+        MakeRouter<R, M, Req, C>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeRouterLayer::new(recognize, idle_timeout, clock))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::Cell,
+        pin::pin,
+        rc::Rc,
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    use pipeline_base::{ManualClock, Stack};
+    use pipeline_test::{assert_pending, assert_ready_err, assert_ready_ok, noop_context};
+
+    use super::*;
+
+    struct Request {
+        path: &'static str,
+    }
+
+    /// Responds with its routing key once it is open.
+    struct RouteService {
+        key: String,
+        open: Rc<Cell<bool>>,
+    }
+    impl Service<Request> for RouteService {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.open.get() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+        fn call(&mut self, req: Request) -> Self::Future {
+            ready(Ok(format!("{} {}", self.key, req.path)))
+        }
+    }
+
+    /// Makes `RouteService` and counts the services made.
+    ///
+    /// The services for keys ending with "slow" are closed.
+    #[derive(Clone)]
+    struct MakeRoute {
+        made: Rc<Cell<usize>>,
+        slow: Rc<Cell<bool>>,
+    }
+    impl Service<String> for MakeRoute {
+        type Response = RouteService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, key: String) -> Self::Future {
+            self.made.set(self.made.get() + 1);
+            let open = if key.ends_with("slow") {
+                self.slow.clone()
+            } else {
+                Rc::new(Cell::new(true))
+            };
+            ready(Ok(RouteService { key, open }))
+        }
+    }
+
+    #[test]
+    fn test_router() {
+        let made = Rc::new(Cell::new(0));
+        let slow = Rc::new(Cell::new(false));
+
+        let stack = Stack::new(MakeRoute {
+            made: made.clone(),
+            slow: slow.clone(),
+        });
+        let make_stack = MakeStack::new::<String>(stack)
+            .push_router::<&str, Request, _>(
+                |authority: &&str, req: &Request| {
+                    let segment = req.path.split('/').nth(1).unwrap_or_default();
+                    format!("{authority}/{segment}")
+                },
+                Duration::from_secs(60),
+            )
+            .check_make_clone::<&str, Request>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        // Make the router.
+        let cx = &mut noop_context();
+        let mut router = assert_ready_ok!(pin!(make_svc.call("example.com")).poll(cx));
+        assert_eq!(made.get(), 0);
+
+        // The slow route is not ready.
+        let mut slow_fut = pin!(router.call(Request { path: "/slow/1" }));
+        assert_pending!(slow_fut.as_mut().poll(cx));
+        assert_eq!(made.get(), 1);
+
+        // Other routes are not held back.
+        for path in ["/users/1", "/users/2"] {
+            assert_ready_ok!(Service::<Request>::poll_ready(&mut router, cx));
+            let resp = assert_ready_ok!(pin!(router.call(Request { path })).poll(cx));
+            assert_eq!(resp, format!("example.com/users {path}"));
+        }
+        assert_eq!(made.get(), 2);
+
+        // The slow route becomes ready.
+        slow.set(true);
+        let resp = assert_ready_ok!(slow_fut.as_mut().poll(cx));
+        assert_eq!(resp, "example.com/slow /slow/1");
+        assert_eq!(made.get(), 2);
+    }
+
+    /// Sets its flag when woken.
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_router_wakes_every_waiter() {
+        let (mock, handle) = pipeline_test::pair::<Request, String>();
+        handle.allow(0);
+        let make_route =
+            tower::service_fn(move |_: String| ready(Ok::<_, Infallible>(mock.clone())));
+        let mut make_svc = MakeStack::new::<String>(Stack::new(make_route))
+            .push_router::<(), Request, _>(
+                |_: &(), _: &Request| "route".to_string(),
+                Duration::from_secs(60),
+            )
+            .into_inner()
+            .into_inner();
+        let cx = &mut noop_context();
+        let mut router = assert_ready_ok!(pin!(make_svc.call(())).poll(cx));
+
+        // Two requests wait on the same route that is not ready.
+        let flags = [Arc::new(Flag::default()), Arc::new(Flag::default())];
+        let wakers = flags.clone().map(Waker::from);
+        let mut futs = [
+            Box::pin(router.call(Request { path: "/1" })),
+            Box::pin(router.call(Request { path: "/2" })),
+        ];
+        for (fut, waker) in futs.iter_mut().zip(&wakers) {
+            assert_pending!(fut.as_mut().poll(&mut Context::from_waker(waker)));
+        }
+
+        // Both are woken once the route is ready and both finish.
+        handle.allow(2);
+        assert!(flags.iter().all(|flag| flag.0.load(Ordering::SeqCst)));
+        for (fut, waker) in futs.iter_mut().zip(&wakers) {
+            assert_pending!(fut.as_mut().poll(&mut Context::from_waker(waker)));
+        }
+        while let Some((req, send)) = handle.next_request() {
+            send.send_response(req.path.to_string());
+        }
+        for (fut, path) in futs.iter_mut().zip(["/1", "/2"]) {
+            assert_eq!(assert_ready_ok!(fut.as_mut().poll(cx)), path);
+        }
+    }
+
+    /// Makes a router over routes that share `mock` and counts the routes made.
+    fn make_router(
+        mock: pipeline_test::Mock<Request, String>,
+        made: Rc<Cell<usize>>,
+        clock: ManualClock,
+    ) -> impl Service<
+        &'static str,
+        Response = impl Service<Request, Response = String, Error = Error>,
+        Error = Infallible,
+    > {
+        let make_route = tower::service_fn(move |_: String| {
+            made.set(made.get() + 1);
+            ready(Ok::<_, Infallible>(mock.clone()))
+        });
+        MakeStack::new::<String>(Stack::new(make_route))
+            .push_router_with_clock::<&str, Request, _, _>(
+                |_: &&str, req: &Request| req.path.to_string(),
+                Duration::from_secs(10),
+                clock,
+            )
+            .into_inner()
+            .into_inner()
+    }
+
+    #[test]
+    fn test_router_remakes_failed_route() {
+        let (mock, handle) = pipeline_test::pair::<Request, String>();
+        let made = Rc::new(Cell::new(0));
+        let mut make_svc = make_router(mock, made.clone(), ManualClock::new());
+        let cx = &mut noop_context();
+        let mut router = assert_ready_ok!(pin!(make_svc.call("example.com")).poll(cx));
+
+        // The route's service fails to become ready.
+        handle.fail_ready("broken");
+        assert_ready_err!(pin!(router.call(Request { path: "/a" })).poll(cx));
+        assert_eq!(made.get(), 1);
+
+        // The next request gets a freshly made service.
+        let mut fut = pin!(router.call(Request { path: "/a" }));
+        assert_pending!(fut.as_mut().poll(cx));
+        assert_eq!(made.get(), 2);
+        let (req, send) = handle.next_request().expect("route called");
+        send.send_response(req.path.to_string());
+        assert_eq!(assert_ready_ok!(fut.poll(cx)), "/a");
+    }
+
+    #[test]
+    fn test_router_evicts_idle_routes() {
+        let (mock, handle) = pipeline_test::pair::<Request, String>();
+        let made = Rc::new(Cell::new(0));
+        let clock = ManualClock::new();
+        let mut make_svc = make_router(mock, made.clone(), clock.clone());
+        let cx = &mut noop_context();
+        let mut router = assert_ready_ok!(pin!(make_svc.call("example.com")).poll(cx));
+
+        let mut call = |path| {
+            let mut fut = pin!(router.call(Request { path }));
+            assert_pending!(fut.as_mut().poll(cx));
+            let (req, send) = handle.next_request().expect("route called");
+            send.send_response(req.path.to_string());
+            assert_ready_ok!(fut.poll(cx))
+        };
+
+        // A route is reused while it keeps receiving requests.
+        call("/a");
+        clock.advance(Duration::from_secs(6));
+        call("/a");
+        clock.advance(Duration::from_secs(6));
+        call("/a");
+        assert_eq!(made.get(), 1);
+
+        // A route that was idle for the timeout is dropped and made again.
+        clock.advance(Duration::from_secs(10));
+        call("/a");
+        assert_eq!(made.get(), 2);
+    }
+
+    #[test]
+    fn test_router_defers_readiness() {
+        let (mock, handle) = pipeline_test::pair::<Request, String>();
+        handle.allow(0);
+        let made = Rc::new(Cell::new(0));
+        let mut make_svc = make_router(mock, made, ManualClock::new());
+        let cx = &mut noop_context();
+        let mut router = assert_ready_ok!(pin!(make_svc.call("example.com")).poll(cx));
+
+        // The router is ready although its route is not; the response future waits instead.
+        assert_ready_ok!(router.poll_ready(cx));
+        let mut fut = pin!(router.call(Request { path: "/a" }));
+        assert_pending!(fut.as_mut().poll(cx));
+        assert!(handle.next_request().is_none());
+
+        // The route becomes ready and the request is sent.
+        handle.allow(1);
+        assert_pending!(fut.as_mut().poll(cx));
+        let (req, send) = handle.next_request().expect("route called");
+        send.send_response(req.path.to_string());
+        assert_eq!(assert_ready_ok!(fut.poll(cx)), "/a");
+    }
+}
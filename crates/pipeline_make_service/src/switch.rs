@@ -0,0 +1,191 @@
+use std::task::{Context, Poll};
+
+use pipeline_base::{
+    Description, Either, EitherFuture, Error, MapResponse, MapResponseFuture, Stack, Switch,
+};
+use tower::Service;
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// Makes services with one of two inner make services, chosen per target by `P`.
+///
+/// The target decides which branch is used only once it is passed to `call`, so `poll_ready` waits for both branches to be ready.
+/// A branch that is not ready holds back the targets of the other branch too.
+///
+/// `A`, `B`: things that make services
+#[derive(Clone, Debug)]
+pub struct MakeSwitch<P, A, B> {
+    predicate: P,
+    a: A,
+    b: B,
+}
+impl<P, A, B> MakeSwitch<P, A, B> {
+    pub fn new(predicate: P, a: A, b: B) -> Self {
+        Self { predicate, a, b }
+    }
+}
+impl<P, A, B, Tgt> Service<Tgt> for MakeSwitch<P, A, B>
+where
+    P: Switch<Tgt>,
+    A: Service<P::A>,
+    A::Error: Into<Error>,
+    B: Service<P::B>,
+    B::Error: Into<Error>,
+{
+    type Response = Either<A::Response, B::Response>;
+    type Error = Error;
+    type Future = EitherFuture<
+        MapResponseFuture<A::Future, fn(A::Response) -> Self::Response>,
+        MapResponseFuture<B::Future, fn(B::Response) -> Self::Response>,
+    >;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let a = self.a.poll_ready(cx).map_err(Into::into)?;
+        let b = self.b.poll_ready(cx).map_err(Into::into)?;
+        if a.is_pending() || b.is_pending() {
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        match self.predicate.switch(target) {
+            Either::A(target) => EitherFuture::A {
+                future: MapResponse::new(&mut self.a, Either::A as fn(_) -> _).call(target),
+            },
+            Either::B(target) => EitherFuture::B {
+                future: MapResponse::new(&mut self.b, Either::B as fn(_) -> _).call(target),
+            },
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Make services with either the current stack or `other`, chosen per target by `predicate`.
+    ///
+    /// The current stack takes the `A` branch and `other` takes the `B` branch.
+    /// The stack is ready only when both branches are ready.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_switch<Tgt, Req, P, B>(
+        self,
+        predicate: P,
+        other: MakeStack<B>,
+    ) -> MakeStack<MakeSwitch<P, M, B>>
+    where
+        MakeSwitch<P, M, B>: MakeServiceFor<Tgt, Req>,
+    {
+        let (a, description) = self.into_inner().into_parts();
+        let (b, other) = other.into_inner().into_parts();
+        let description = description.map(|mut description| {
+            let other = other.unwrap_or_else(Description::of::<B>);
+            description.push_branched("MakeSwitch", vec![other]);
+            description
+        });
+        let stack = Stack::from_parts(MakeSwitch::new(predicate, a, b), description);
+        MakeStack::new::<Tgt>(stack).check_make::<Tgt, Req>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Future, Ready},
+        pin::pin,
+    };
+
+    use pipeline_base::Describe;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct Target {
+        addr: &'static str,
+        http: bool,
+    }
+
+    struct MakeEcho;
+    impl Service<&'static str> for MakeEcho {
+        type Response = EchoService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: &'static str) -> Self::Future {
+            ready(Ok(EchoService))
+        }
+    }
+
+    /// Responds with the request in upper case.
+    struct UpperService;
+    impl Service<String> for UpperService {
+        type Response = String;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: String) -> Self::Future {
+            ready(Ok(req.to_uppercase()))
+        }
+    }
+
+    struct MakeUpper;
+    impl Service<Target> for MakeUpper {
+        type Response = UpperService;
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Self::Error>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, _: Target) -> Self::Future {
+            ready(Ok(UpperService))
+        }
+    }
+
+    #[test]
+    fn test_switch() {
+        let http = MakeStack::new::<&str>(Stack::described(MakeEcho));
+        let opaque = MakeStack::new::<Target>(Stack::new(MakeUpper));
+        let make_stack = http.push_switch::<Target, String, _, _>(
+            |target: Target| {
+                if target.http {
+                    Either::A(target.addr)
+                } else {
+                    Either::B(target)
+                }
+            },
+            opaque,
+        );
+        assert_eq!(
+            make_stack.describe().to_string(),
+            "MakeEcho\nMakeSwitch\n└── MakeUpper\n"
+        );
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let targets = [
+            Target {
+                addr: "10.0.0.1:80",
+                http: true,
+            },
+            Target {
+                addr: "10.0.0.2:5432",
+                http: false,
+            },
+        ];
+        let expected = ["hello", "HELLO"];
+        let cx = &mut noop_context();
+        for (target, expected) in targets.into_iter().zip(expected) {
+            assert_ready_ok!(tower::MakeService::<Target, String>::poll_ready(
+                &mut make_svc,
+                cx
+            ));
+            let mut svc = assert_ready_ok!(pin!(make_svc.call(target)).poll(cx));
+            let resp = assert_ready_ok!(pin!(svc.call("hello".to_string())).poll(cx));
+            assert_eq!(resp, expected);
+        }
+    }
+}
@@ -0,0 +1,396 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+use pipeline_base::{Error, Param};
+use tokio::time::{sleep, Sleep};
+use tower::{Layer, Service};
+
+use crate::{MakeServiceFor, MakeStack};
+
+/// The time a made service has to respond to a request, a target parameter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestTimeout(pub Duration);
+
+/// Bounds the time every service made by `M` has to respond by the `RequestTimeout` of its target.
+///
+/// `M`: a thing that makes services
+#[derive(Clone, Debug)]
+pub struct MakeRequestTimeout<M> {
+    inner: M,
+}
+impl<M, Tgt> Service<Tgt> for MakeRequestTimeout<M>
+where
+    Tgt: Param<RequestTimeout> + fmt::Debug + Clone,
+    M: Service<Tgt>,
+{
+    type Response = TimeoutService<M::Response, Tgt>;
+    type Error = M::Error;
+    type Future = MakeRequestTimeoutFuture<M::Future, Tgt>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        let RequestTimeout(timeout) = target.param();
+        MakeRequestTimeoutFuture {
+            future: self.inner.call(target.clone()),
+            timeout,
+            target: Some(Arc::new(target)),
+        }
+    }
+}
+
+pin_project! {
+    pub struct MakeRequestTimeoutFuture<F, Tgt> {
+        #[pin]
+        future: F,
+        timeout: Duration,
+        target: Option<Arc<Tgt>>,
+    }
+}
+impl<F, Tgt, S, E> Future for MakeRequestTimeoutFuture<F, Tgt>
+where
+    F: Future<Output = Result<S, E>>,
+{
+    type Output = Result<TimeoutService<S, Tgt>, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = match this.future.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+        let target = this.target.take().expect("polled after completion");
+        let timeout = *this.timeout;
+        Poll::Ready(res.map(|inner| TimeoutService {
+            inner,
+            timeout,
+            target,
+        }))
+    }
+}
+
+/// Fails requests that are not responded to within the timeout with `ResponseTimeout`.
+#[derive(Debug)]
+pub struct TimeoutService<S, Tgt> {
+    inner: S,
+    timeout: Duration,
+    target: Arc<Tgt>,
+}
+impl<S: Clone, Tgt> Clone for TimeoutService<S, Tgt> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            timeout: self.timeout,
+            target: self.target.clone(),
+        }
+    }
+}
+impl<S, Tgt, Req> Service<Req> for TimeoutService<S, Tgt>
+where
+    S: Service<Req>,
+    S::Error: Into<Error>,
+    Tgt: fmt::Debug,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = TimeoutFuture<S::Future, Arc<Tgt>>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        TimeoutFuture {
+            future: self.inner.call(req),
+            sleep: sleep(self.timeout),
+            timeout: self.timeout,
+            target: self.target.clone(),
+            error: ResponseTimeout::boxed,
+        }
+    }
+}
+
+/// Bounds the time `M` has to make a service.
+///
+/// `M`: a thing that makes services
+#[derive(Clone, Debug)]
+pub struct MakeServiceTimeout<M> {
+    inner: M,
+    timeout: Duration,
+}
+impl<M, Tgt> Service<Tgt> for MakeServiceTimeout<M>
+where
+    Tgt: fmt::Debug + Clone,
+    M: Service<Tgt>,
+    M::Error: Into<Error>,
+{
+    type Response = M::Response;
+    type Error = Error;
+    type Future = TimeoutFuture<M::Future, Tgt>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        TimeoutFuture {
+            future: self.inner.call(target.clone()),
+            sleep: sleep(self.timeout),
+            timeout: self.timeout,
+            target,
+            error: MakeTimeout::boxed,
+        }
+    }
+}
+
+pin_project! {
+    /// Fails with the error built by `error` if `future` does not finish within the timeout.
+    ///
+    /// `Tgt` is only formatted once the timeout fires.
+    pub struct TimeoutFuture<F, Tgt> {
+        #[pin]
+        future: F,
+        #[pin]
+        sleep: Sleep,
+        timeout: Duration,
+        target: Tgt,
+        error: fn(&dyn fmt::Debug, Duration) -> Error,
+    }
+}
+impl<F, Tgt, T, E> Future for TimeoutFuture<F, Tgt>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Error>,
+    Tgt: fmt::Debug,
+{
+    type Output = Result<T, Error>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(res) = this.future.poll(cx) {
+            return Poll::Ready(res.map_err(Into::into));
+        }
+        match this.sleep.poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err((this.error)(&*this.target, *this.timeout))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A made service did not respond within its `RequestTimeout`.
+#[derive(Clone, Debug)]
+pub struct ResponseTimeout {
+    target: Arc<str>,
+    timeout: Duration,
+}
+impl ResponseTimeout {
+    fn boxed(target: &dyn fmt::Debug, timeout: Duration) -> Error {
+        let target = format!("{target:?}").into();
+        Box::new(Self { target, timeout })
+    }
+
+    /// The `Debug` description of the target the service was made for
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+impl fmt::Display for ResponseTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "response timed out after {:?} for target {}",
+            self.timeout, self.target
+        )
+    }
+}
+impl std::error::Error for ResponseTimeout {}
+
+/// A service was not made within the make timeout.
+#[derive(Clone, Debug)]
+pub struct MakeTimeout {
+    target: Arc<str>,
+    timeout: Duration,
+}
+impl MakeTimeout {
+    fn boxed(target: &dyn fmt::Debug, timeout: Duration) -> Error {
+        let target = format!("{target:?}").into();
+        Box::new(Self { target, timeout })
+    }
+
+    /// The `Debug` description of the target
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+impl fmt::Display for MakeTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "making a service timed out after {:?} for target {}",
+            self.timeout, self.target
+        )
+    }
+}
+impl std::error::Error for MakeTimeout {}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MakeRequestTimeoutLayer;
+impl<M> Layer<M> for MakeRequestTimeoutLayer {
+    type Service = MakeRequestTimeout<M>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeRequestTimeout { inner }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MakeServiceTimeoutLayer(Duration);
+impl MakeServiceTimeoutLayer {
+    pub fn new(timeout: Duration) -> Self {
+        Self(timeout)
+    }
+}
+impl<M> Layer<M> for MakeServiceTimeoutLayer {
+    type Service = MakeServiceTimeout<M>;
+    fn layer(&self, inner: M) -> Self::Service {
+        MakeServiceTimeout {
+            inner,
+            timeout: self.0,
+        }
+    }
+}
+
+impl<M> MakeStack<M> {
+    /// Fail requests to the made services that take longer than the `RequestTimeout` of their target.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_request_timeout<Tgt, Req>(self) -> MakeStack<MakeRequestTimeout<M>>
+    where
+        MakeRequestTimeout<M>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeRequestTimeoutLayer)
+    }
+
+    /// Fail making services that takes longer than `timeout`.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    ///
+    /// `Req`: the request type after the layer is applied
+    pub fn push_make_timeout<Tgt, Req>(self, timeout: Duration) -> MakeStack<MakeServiceTimeout<M>>
+    where
+        MakeServiceTimeout<M>: MakeServiceFor<Tgt, Req>,
+    {
+        self.push::<Tgt, Req, _>(MakeServiceTimeoutLayer::new(timeout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use pipeline_base::Stack;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct Endpoint {
+        addr: &'static str,
+    }
+    impl Param<RequestTimeout> for Endpoint {
+        fn param(&self) -> RequestTimeout {
+            RequestTimeout(Duration::from_secs(1))
+        }
+    }
+
+    /// Responds with the request after the delay in the request.
+    #[derive(Debug)]
+    struct SlowService;
+    impl Service<(Duration, &'static str)> for SlowService {
+        type Response = &'static str;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, (delay, req): (Duration, &'static str)) -> Self::Future {
+            Box::pin(async move {
+                sleep(delay).await;
+                Ok(req)
+            })
+        }
+    }
+
+    /// Makes `SlowService` after a delay that depends on the address.
+    struct MakeSlow;
+    impl Service<Endpoint> for MakeSlow {
+        type Response = SlowService;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, target: Endpoint) -> Self::Future {
+            let delay = if target.addr.ends_with("slow") {
+                Duration::from_secs(10)
+            } else {
+                Duration::ZERO
+            };
+            Box::pin(async move {
+                sleep(delay).await;
+                Ok(SlowService)
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_request_timeout() {
+        let make_stack = MakeStack::new::<Endpoint>(Stack::new(MakeSlow))
+            .push_request_timeout::<Endpoint, (Duration, &'static str)>();
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let mut svc = ServiceExt::<Endpoint>::oneshot(&mut make_svc, Endpoint { addr: "a" })
+            .await
+            .unwrap();
+
+        let req = (Duration::from_millis(999), "fast");
+        let resp = svc.ready().await.unwrap().call(req).await.unwrap();
+        assert_eq!(resp, "fast");
+
+        let req = (Duration::from_secs(2), "slow");
+        let error = svc.ready().await.unwrap().call(req).await.unwrap_err();
+        let error = error.downcast_ref::<ResponseTimeout>().unwrap();
+        assert_eq!(error.target(), r#"Endpoint { addr: "a" }"#);
+        assert_eq!(error.timeout(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_make_timeout() {
+        let make_stack = MakeStack::new::<Endpoint>(Stack::new(MakeSlow))
+            .push_make_timeout::<Endpoint, (Duration, &'static str)>(Duration::from_secs(5));
+        let mut make_svc = make_stack.into_inner().into_inner();
+
+        let svc = ServiceExt::<Endpoint>::oneshot(&mut make_svc, Endpoint { addr: "a" }).await;
+        assert!(svc.is_ok());
+
+        let error = ServiceExt::<Endpoint>::oneshot(&mut make_svc, Endpoint { addr: "b-slow" })
+            .await
+            .unwrap_err();
+        let error = error.downcast_ref::<MakeTimeout>().unwrap();
+        assert_eq!(error.target(), r#"Endpoint { addr: "b-slow" }"#);
+        assert_eq!(
+            error.to_string(),
+            r#"making a service timed out after 5s for target Endpoint { addr: "b-slow" }"#
+        );
+    }
+}
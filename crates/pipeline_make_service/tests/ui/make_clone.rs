@@ -0,0 +1,27 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use pipeline_base::Stack;
+use pipeline_make_service::MakeStack;
+use pipeline_test::EchoService;
+use tower::Service;
+
+struct MakeEcho;
+impl Service<String> for MakeEcho {
+    type Response = EchoService;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, _: String) -> Self::Future {
+        ready(Ok(EchoService))
+    }
+}
+
+fn main() {
+    MakeStack::new::<String>(Stack::new(MakeEcho)).check_make_clone::<String, String>();
+}
@@ -0,0 +1,12 @@
+use pipeline_base::Stack;
+use pipeline_make_service::MakeStack;
+use pipeline_test::{pair, Mock, TraceBody, TraceLayer};
+
+fn main() {
+    let (make, _handle) = pair::<String, Mock<TraceBody, TraceBody>>();
+
+    // The trace layer is pushed with the target type from before the `u16` conversion.
+    MakeStack::new::<String>(Stack::new(make))
+        .push_map_target::<u16, TraceBody, _>(|port: u16| format!("localhost:{port}"))
+        .push_on_service::<String, TraceBody, _>(TraceLayer::new("req", "resp"));
+}
@@ -0,0 +1,8 @@
+use pipeline_base::Stack;
+use pipeline_make_service::MakeStack;
+use pipeline_test::{pair, Mock};
+
+fn main() {
+    let (make, _handle) = pair::<String, Mock<String, String>>();
+    MakeStack::new::<String>(Stack::new(make)).check_make::<String, u16>();
+}
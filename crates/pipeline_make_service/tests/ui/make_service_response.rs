@@ -0,0 +1,8 @@
+use pipeline_base::Stack;
+use pipeline_make_service::MakeStack;
+use pipeline_test::{pair, Mock};
+
+fn main() {
+    let (make, _handle) = pair::<String, Mock<u8, u16>>();
+    MakeStack::new::<String>(Stack::new(make)).check_make_service_response::<String, u8, u8>();
+}
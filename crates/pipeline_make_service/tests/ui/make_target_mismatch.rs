@@ -0,0 +1,8 @@
+use pipeline_base::Stack;
+use pipeline_make_service::MakeStack;
+use pipeline_test::{pair, EchoService};
+
+fn main() {
+    let (make, _handle) = pair::<String, EchoService>();
+    MakeStack::new::<u16>(Stack::new(make));
+}
@@ -0,0 +1,224 @@
+use std::{fmt, rc::Rc, sync::Arc};
+
+use tower::layer::layer_fn;
+
+use crate::{NewService, NewServiceStack};
+
+/// A type-erased `NewService + Send + Sync`.
+pub struct BoxNewService<Tgt, S>(Box<dyn NewService<Tgt, Service = S> + Send + Sync>);
+impl<Tgt, S> BoxNewService<Tgt, S> {
+    pub fn new<N>(inner: N) -> Self
+    where
+        N: NewService<Tgt, Service = S> + Send + Sync + 'static,
+    {
+        Self(Box::new(inner))
+    }
+}
+impl<Tgt, S> NewService<Tgt> for BoxNewService<Tgt, S> {
+    type Service = S;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        self.0.new_service(target)
+    }
+}
+impl<Tgt, S> fmt::Debug for BoxNewService<Tgt, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxNewService").finish()
+    }
+}
+
+/// The same as `BoxNewService` but the inner `NewService` need not be `Send` or `Sync`.
+pub struct UnsyncBoxNewService<Tgt, S>(Box<dyn NewService<Tgt, Service = S>>);
+impl<Tgt, S> UnsyncBoxNewService<Tgt, S> {
+    pub fn new<N>(inner: N) -> Self
+    where
+        N: NewService<Tgt, Service = S> + 'static,
+    {
+        Self(Box::new(inner))
+    }
+}
+impl<Tgt, S> NewService<Tgt> for UnsyncBoxNewService<Tgt, S> {
+    type Service = S;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        self.0.new_service(target)
+    }
+}
+impl<Tgt, S> fmt::Debug for UnsyncBoxNewService<Tgt, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnsyncBoxNewService").finish()
+    }
+}
+
+/// A type-erased `NewService + Send + Sync` that is cheap to clone.
+pub struct ArcNewService<Tgt, S>(Arc<dyn NewService<Tgt, Service = S> + Send + Sync>);
+impl<Tgt, S> ArcNewService<Tgt, S> {
+    pub fn new<N>(inner: N) -> Self
+    where
+        N: NewService<Tgt, Service = S> + Send + Sync + 'static,
+    {
+        Self(Arc::new(inner))
+    }
+}
+impl<Tgt, S> Clone for ArcNewService<Tgt, S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<Tgt, S> NewService<Tgt> for ArcNewService<Tgt, S> {
+    type Service = S;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        self.0.new_service(target)
+    }
+}
+impl<Tgt, S> fmt::Debug for ArcNewService<Tgt, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArcNewService").finish()
+    }
+}
+
+/// The same as `ArcNewService` but the inner `NewService` need not be `Send` or `Sync`.
+pub struct RcNewService<Tgt, S>(Rc<dyn NewService<Tgt, Service = S>>);
+impl<Tgt, S> RcNewService<Tgt, S> {
+    pub fn new<N>(inner: N) -> Self
+    where
+        N: NewService<Tgt, Service = S> + 'static,
+    {
+        Self(Rc::new(inner))
+    }
+}
+impl<Tgt, S> Clone for RcNewService<Tgt, S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<Tgt, S> NewService<Tgt> for RcNewService<Tgt, S> {
+    type Service = S;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        self.0.new_service(target)
+    }
+}
+impl<Tgt, S> fmt::Debug for RcNewService<Tgt, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RcNewService").finish()
+    }
+}
+
+impl<N> NewServiceStack<N> {
+    /// Erase the type of the stack.
+    ///
+    /// `Tgt`: the target type of the stack
+    pub fn push_box_new<Tgt>(self) -> NewServiceStack<BoxNewService<Tgt, N::Service>>
+    where
+        N: NewService<Tgt> + Send + Sync + 'static,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("BoxNewService", layer_fn(BoxNewService::new));
+        NewServiceStack::new(stack)
+    }
+
+    /// The same as `push_box_new` but the stack need not be `Send` or `Sync`.
+    pub fn push_unsync_box_new<Tgt>(self) -> NewServiceStack<UnsyncBoxNewService<Tgt, N::Service>>
+    where
+        N: NewService<Tgt> + 'static,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("UnsyncBoxNewService", layer_fn(UnsyncBoxNewService::new));
+        NewServiceStack::new(stack)
+    }
+
+    /// Erase the type of the stack and make it cheap to clone.
+    ///
+    /// `Tgt`: the target type of the stack
+    pub fn push_arc_new<Tgt>(self) -> NewServiceStack<ArcNewService<Tgt, N::Service>>
+    where
+        N: NewService<Tgt> + Send + Sync + 'static,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("ArcNewService", layer_fn(ArcNewService::new));
+        NewServiceStack::new(stack)
+    }
+
+    /// The same as `push_arc_new` but the stack need not be `Send` or `Sync`.
+    pub fn push_rc_new<Tgt>(self) -> NewServiceStack<RcNewService<Tgt, N::Service>>
+    where
+        N: NewService<Tgt> + 'static,
+    {
+        let stack = self
+            .into_inner()
+            .push_named("RcNewService", layer_fn(RcNewService::new));
+        NewServiceStack::new(stack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use pipeline_base::Stack;
+
+    use super::*;
+
+    struct NewLen;
+    impl NewService<&str> for NewLen {
+        type Service = usize;
+        fn new_service(&self, target: &str) -> Self::Service {
+            target.len()
+        }
+    }
+
+    /// Builds ids counting up from zero.
+    struct NewId(Cell<usize>);
+    impl<Tgt> NewService<Tgt> for NewId {
+        type Service = usize;
+        fn new_service(&self, _: Tgt) -> Self::Service {
+            let id = self.0.get();
+            self.0.set(id + 1);
+            id
+        }
+    }
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn test_box_new() {
+        /// The stack type can be named in a struct field.
+        struct Proxy {
+            new_svc: BoxNewService<&'static str, usize>,
+        }
+
+        let stack = NewServiceStack::new(Stack::new(NewLen)).push_box_new::<&str>();
+        let proxy = Proxy {
+            new_svc: stack.into_inner().into_inner(),
+        };
+        assert_send_sync(&proxy.new_svc);
+        assert_eq!(proxy.new_svc.new_service("hello"), 5);
+
+        let stack =
+            NewServiceStack::new(Stack::new(NewId(Cell::new(0)))).push_unsync_box_new::<&str>();
+        let new_svc: UnsyncBoxNewService<&str, usize> = stack.into_inner().into_inner();
+        assert_eq!(new_svc.new_service("hello"), 0);
+        assert_eq!(new_svc.new_service("hello"), 1);
+    }
+
+    #[test]
+    fn test_arc_new() {
+        let stack = NewServiceStack::new(Stack::new(NewLen)).push_arc_new::<&str>();
+        let new_svc: ArcNewService<&str, usize> = stack.into_inner().into_inner();
+        let cloned = new_svc.clone();
+        assert_send_sync(&cloned);
+        assert_eq!(cloned.new_service("hello"), 5);
+
+        // Clones share the same inner `NewService`.
+        let stack = NewServiceStack::new(Stack::new(NewId(Cell::new(0)))).push_rc_new::<&str>();
+        let new_svc: RcNewService<&str, usize> = stack.into_inner().into_inner();
+        let cloned = new_svc.clone();
+        assert_eq!(new_svc.new_service("hello"), 0);
+        assert_eq!(cloned.new_service("hello"), 1);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    hash::Hash,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use pipeline_base::{Cache, Clock, SystemClock};
+use tower::Layer;
+
+use crate::{NewService, NewServiceStack};
+
+/// Builds at most one service per target and hands out clones of it.
+///
+/// A cached service is evicted after not being handed out for the idle timeout.
+///
+/// `S`: the service built by `N`
+pub struct NewCache<N, Tgt, S, C = SystemClock> {
+    inner: N,
+    cache: Arc<Mutex<Cache<Tgt, S, C>>>,
+}
+impl<N, Tgt, S, C> NewCache<N, Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock,
+{
+    pub fn new(inner: N, idle_timeout: Duration, clock: C) -> Self {
+        let cache = Cache::new(idle_timeout, clock);
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(cache)),
+        }
+    }
+}
+/// Clones share the same cache.
+impl<N: Clone, Tgt, S, C> Clone for NewCache<N, Tgt, S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            cache: self.cache.clone(),
+        }
+    }
+}
+impl<N, Tgt, C> NewService<Tgt> for NewCache<N, Tgt, N::Service, C>
+where
+    Tgt: Hash + Eq + Clone,
+    N: NewService<Tgt>,
+    N::Service: Clone,
+    C: Clock,
+{
+    type Service = N::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        if let Some(service) = self.cache.lock().unwrap().get(&target) {
+            return service;
+        }
+        // Build without holding the lock so `inner` may use the cache too.
+        let service = self.inner.new_service(target.clone());
+        // Another caller may have built a service for the same target meanwhile; keep the first one.
+        self.cache.lock().unwrap().get_or_insert(target, service)
+    }
+}
+
+pub struct NewCacheLayer<Tgt, S, C = SystemClock> {
+    idle_timeout: Duration,
+    clock: C,
+    _cache: PhantomData<fn(Tgt) -> S>,
+}
+impl<Tgt, S, C> NewCacheLayer<Tgt, S, C> {
+    pub fn new(idle_timeout: Duration, clock: C) -> Self {
+        Self {
+            idle_timeout,
+            clock,
+            _cache: PhantomData,
+        }
+    }
+}
+impl<Tgt, S, C: Clone> Clone for NewCacheLayer<Tgt, S, C> {
+    fn clone(&self) -> Self {
+        Self::new(self.idle_timeout, self.clock.clone())
+    }
+}
+/// Every layered service gets its own cache.
+impl<N, Tgt, S, C> Layer<N> for NewCacheLayer<Tgt, S, C>
+where
+    Tgt: Hash + Eq,
+    S: Clone,
+    C: Clock + Clone,
+{
+    type Service = NewCache<N, Tgt, S, C>;
+    fn layer(&self, inner: N) -> Self::Service {
+        NewCache::new(inner, self.idle_timeout, self.clock.clone())
+    }
+}
+
+impl<N> NewServiceStack<N> {
+    /// Build at most one service per target and hand out clones of it.
+    ///
+    /// A cached service is evicted after not being handed out for `idle_timeout`.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    pub fn push_cache<Tgt>(
+        self,
+        idle_timeout: Duration,
+    ) -> NewServiceStack<NewCache<N, Tgt, N::Service, SystemClock>>
+    where
+        Tgt: Hash + Eq + Clone,
+        N: NewService<Tgt>,
+        N::Service: Clone,
+    {
+        self.push_cache_with_clock::<Tgt, _>(idle_timeout, SystemClock)
+    }
+
+    /// The same as `push_cache` but reads the time from `clock`.
+    pub fn push_cache_with_clock<Tgt, C>(
+        self,
+        idle_timeout: Duration,
+        clock: C,
+    ) -> NewServiceStack<NewCache<N, Tgt, N::Service, C>>
+    where
+        Tgt: Hash + Eq + Clone,
+        N: NewService<Tgt>,
+        N::Service: Clone,
+        C: Clock + Clone,
+    {
+        self.push::<Tgt, _>(NewCacheLayer::new(idle_timeout, clock))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+
+    use pipeline_base::{ManualClock, Stack};
+
+    use super::*;
+
+    /// Builds ids counting up from zero.
+    struct NewId(Cell<usize>);
+    impl<Tgt> NewService<Tgt> for NewId {
+        type Service = usize;
+        fn new_service(&self, _: Tgt) -> Self::Service {
+            let id = self.0.get();
+            self.0.set(id + 1);
+            id
+        }
+    }
+
+    #[test]
+    fn test_cache() {
+        let clock = ManualClock::new();
+
+        let stack = NewServiceStack::new(Stack::new(NewId(Cell::new(0))))
+            .push_cache_with_clock::<&str, _>(Duration::from_secs(10), clock.clone());
+        let new_svc = stack.into_inner().into_inner();
+
+        assert_eq!(new_svc.new_service("a"), 0);
+        assert_eq!(new_svc.new_service("b"), 1);
+        assert_eq!(new_svc.new_service("a"), 0);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(new_svc.new_service("a"), 2);
+        assert_eq!(new_svc.new_service("b"), 3);
+    }
+
+    type SharedCache = Arc<Mutex<Cache<&'static str, usize, ManualClock>>>;
+
+    /// Builds ids counting up from zero, but lets another service win the race to `cache` for every target first.
+    struct NewRacing {
+        id: Cell<usize>,
+        cache: RefCell<Option<SharedCache>>,
+    }
+    impl NewService<&'static str> for NewRacing {
+        type Service = usize;
+        fn new_service(&self, target: &'static str) -> Self::Service {
+            if let Some(cache) = &*self.cache.borrow() {
+                cache.lock().unwrap().insert(target, 100);
+            }
+            let id = self.id.get();
+            self.id.set(id + 1);
+            id
+        }
+    }
+
+    #[test]
+    fn test_cache_keeps_the_first_service() {
+        let inner = NewRacing {
+            id: Cell::new(0),
+            cache: RefCell::new(None),
+        };
+        let new_svc = NewCache::new(inner, Duration::from_secs(10), ManualClock::new());
+        *new_svc.inner.cache.borrow_mut() = Some(new_svc.cache.clone());
+
+        assert_eq!(new_svc.new_service("a"), 100);
+        assert_eq!(new_svc.new_service("a"), 100);
+        assert_eq!(new_svc.inner.id.get(), 1);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::NewService;
+
+/// Builds a clone of the same service for every target.
+#[derive(Clone, Debug)]
+pub struct NewCloneService<S>(S);
+impl<S> NewCloneService<S> {
+    pub fn new(service: S) -> Self {
+        Self(service)
+    }
+}
+impl<S> From<S> for NewCloneService<S> {
+    fn from(service: S) -> Self {
+        Self::new(service)
+    }
+}
+impl<S, Tgt> NewService<Tgt> for NewCloneService<S>
+where
+    S: Clone,
+{
+    type Service = S;
+
+    fn new_service(&self, _: Tgt) -> Self::Service {
+        self.0.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_new_clone_service() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let new_svc = NewCloneService::new(hits.clone());
+
+        // Every target gets a clone of the same service.
+        let a = NewService::<&str>::new_service(&new_svc, "a");
+        let b = NewService::<u16>::new_service(&new_svc, 80);
+        a.fetch_add(1, Ordering::SeqCst);
+        b.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+}
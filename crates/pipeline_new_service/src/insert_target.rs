@@ -0,0 +1,138 @@
+use std::marker::PhantomData;
+
+use pipeline_base::{InsertTarget, Param};
+use tower::Layer;
+
+use crate::{NewService, NewServiceStack};
+
+/// Makes services that insert the parameter `P` of their target into every request.
+///
+/// Unlike the services pushed with `push_on_service`, the inner services see part of the target metadata in the request.
+///
+/// `X`: how the parameter is inserted into the request
+pub struct NewInsertTarget<P, X, N> {
+    inner: N,
+    insert: X,
+    _param: PhantomData<fn() -> P>,
+}
+impl<P, X, N> NewInsertTarget<P, X, N> {
+    pub fn new(inner: N, insert: X) -> Self {
+        Self {
+            inner,
+            insert,
+            _param: PhantomData,
+        }
+    }
+}
+impl<P, X: Clone, N: Clone> Clone for NewInsertTarget<P, X, N> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner.clone(), self.insert.clone())
+    }
+}
+impl<P, X, N, Tgt> NewService<Tgt> for NewInsertTarget<P, X, N>
+where
+    Tgt: Param<P>,
+    X: Clone,
+    N: NewService<Tgt>,
+{
+    type Service = InsertTarget<N::Service, P, X>;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        let param = target.param();
+        InsertTarget::new(self.inner.new_service(target), param, self.insert.clone())
+    }
+}
+
+pub struct NewInsertTargetLayer<P, X> {
+    insert: X,
+    _param: PhantomData<fn() -> P>,
+}
+impl<P, X> NewInsertTargetLayer<P, X> {
+    pub fn new(insert: X) -> Self {
+        Self {
+            insert,
+            _param: PhantomData,
+        }
+    }
+}
+impl<P, X: Clone> Clone for NewInsertTargetLayer<P, X> {
+    fn clone(&self) -> Self {
+        Self::new(self.insert.clone())
+    }
+}
+impl<P, X, N> Layer<N> for NewInsertTargetLayer<P, X>
+where
+    X: Clone,
+{
+    type Service = NewInsertTarget<P, X, N>;
+    fn layer(&self, inner: N) -> Self::Service {
+        NewInsertTarget::new(inner, self.insert.clone())
+    }
+}
+
+impl<N> NewServiceStack<N> {
+    /// Insert the parameter `P` of the target into every request of the made services.
+    ///
+    /// With the `http` feature, `pipeline_base::InsertExtension` inserts it into the extensions of `http::Request`s.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    pub fn push_insert_target<Tgt, P, X>(
+        self,
+        insert: X,
+    ) -> NewServiceStack<NewInsertTarget<P, X, N>>
+    where
+        X: Clone,
+        NewInsertTarget<P, X, N>: NewService<Tgt>,
+    {
+        self.push::<Tgt, _>(NewInsertTargetLayer::new(insert))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, net::SocketAddr, pin::pin};
+
+    use pipeline_base::{InsertExtension, Stack};
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService};
+    use tower::Service;
+
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    struct Addr(SocketAddr);
+
+    struct Endpoint {
+        addr: SocketAddr,
+    }
+    impl Param<Addr> for Endpoint {
+        fn param(&self) -> Addr {
+            Addr(self.addr)
+        }
+    }
+
+    #[derive(Clone)]
+    struct NewEcho;
+    impl<Tgt> NewService<Tgt> for NewEcho {
+        type Service = EchoService;
+        fn new_service(&self, _: Tgt) -> Self::Service {
+            EchoService
+        }
+    }
+
+    #[test]
+    fn test_insert_extension() {
+        let stack = NewServiceStack::new(Stack::new(NewEcho))
+            .push_insert_target::<Endpoint, Addr, _>(InsertExtension)
+            .check_new_service::<Endpoint, http::Request<()>>()
+            .check_new_clone::<Endpoint>();
+        let new_svc = stack.into_inner().into_inner();
+
+        let addr: SocketAddr = "10.0.0.1:80".parse().unwrap();
+        let mut svc = new_svc.new_service(Endpoint { addr });
+
+        // The echoed request carries the address.
+        let cx = &mut noop_context();
+        let resp = assert_ready_ok!(pin!(svc.call(http::Request::new(()))).poll(cx));
+        assert_eq!(resp.extensions().get::<Addr>(), Some(&Addr(addr)));
+    }
+}
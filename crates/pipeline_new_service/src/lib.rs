@@ -1,14 +1,98 @@
-use pipeline_base::Stack;
+use std::sync::Arc;
+
+use pipeline_base::{Describe, Description, Either, ServiceFor, Stack};
+use tower::Layer;
+
+mod boxed;
+mod cache;
+mod clone;
+mod insert_target;
+mod make_service;
+mod map_target;
+#[cfg(any(test, feature = "test-util"))]
+mod mock;
+mod new_fn;
+mod on_service;
+
+pub use boxed::{ArcNewService, BoxNewService, RcNewService, UnsyncBoxNewService};
+pub use cache::{NewCache, NewCacheLayer};
+pub use clone::NewCloneService;
+pub use insert_target::{NewInsertTarget, NewInsertTargetLayer};
+pub use make_service::IntoMakeService;
+pub use map_target::{NewMapTarget, NewMapTargetLayer};
+#[cfg(any(test, feature = "test-util"))]
+pub use mock::{new_pair, NewHandle, NewMock};
+pub use new_fn::{new_service_fn, NewServiceFn};
+pub use on_service::{NewOnService, NewOnServiceLayer};
 
 /// Basically a `tower::MakeService`
+#[diagnostic::on_unimplemented(
+    message = "the stack `{Self}` does not build services for `{Tgt}`",
+    label = "this stack does not build services for `{Tgt}`",
+    note = "the outermost type is the layer pushed last; check the `Tgt` it was pushed with"
+)]
 pub trait NewService<Tgt> {
     type Service;
 
     fn new_service(&self, target: Tgt) -> Self::Service;
 }
 
+impl<Tgt, A, B> NewService<Tgt> for Either<A, B>
+where
+    A: NewService<Tgt>,
+    B: NewService<Tgt>,
+{
+    type Service = Either<A::Service, B::Service>;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        match self {
+            Either::A(a) => Either::A(a.new_service(target)),
+            Either::B(b) => Either::B(b.new_service(target)),
+        }
+    }
+}
+
+impl<Tgt, N> NewService<Tgt> for &N
+where
+    N: NewService<Tgt> + ?Sized,
+{
+    type Service = N::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        (**self).new_service(target)
+    }
+}
+
+impl<Tgt, N> NewService<Tgt> for Box<N>
+where
+    N: NewService<Tgt> + ?Sized,
+{
+    type Service = N::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        (**self).new_service(target)
+    }
+}
+
+impl<Tgt, N> NewService<Tgt> for Arc<N>
+where
+    N: NewService<Tgt> + ?Sized,
+{
+    type Service = N::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        (**self).new_service(target)
+    }
+}
+
 pub struct NewServiceStack<S>(Stack<S>);
 
+impl<S> Describe for NewServiceStack<S> {
+    fn describe(&self) -> Description {
+        self.0.describe()
+    }
+}
+
 impl<S> NewServiceStack<S> {
     pub fn new(stack: Stack<S>) -> Self {
         NewServiceStack(stack)
@@ -18,10 +102,90 @@ impl<S> NewServiceStack<S> {
         self.0
     }
 
+    /// Push an outer layer onto the stack.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    pub fn push<Tgt, L>(self, layer: L) -> NewServiceStack<L::Service>
+    where
+        L: Layer<S>,
+        L::Service: NewService<Tgt>,
+    {
+        let stack = self.into_inner();
+        let stack = stack.push(layer);
+        NewServiceStack::new(stack).check_new::<Tgt>()
+    }
+
+    /// Make sure the inner service is a certain `NewService`.
     pub fn check_new<Tgt>(self) -> Self
     where
         S: NewService<Tgt>,
     {
         self
     }
+
+    /// Make sure the inner service is a certain `NewService` and the services it builds are a certain `Service`.
+    pub fn check_new_service<Tgt, Req>(self) -> Self
+    where
+        S: NewService<Tgt>,
+        S::Service: ServiceFor<Req>,
+    {
+        self
+    }
+
+    /// Make sure the inner service is a certain `NewService` and is `Clone`.
+    pub fn check_new_clone<Tgt>(self) -> Self
+    where
+        S: NewService<Tgt> + Clone,
+    {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NewLen;
+    impl NewService<&str> for NewLen {
+        type Service = usize;
+        fn new_service(&self, target: &str) -> Self::Service {
+            target.len()
+        }
+    }
+
+    struct NewUpper;
+    impl NewService<&str> for NewUpper {
+        type Service = String;
+        fn new_service(&self, target: &str) -> Self::Service {
+            target.to_uppercase()
+        }
+    }
+
+    #[test]
+    fn test_either() {
+        let new_svc: Either<NewLen, NewUpper> = Either::A(NewLen);
+        assert_eq!(new_svc.new_service("hello"), Either::A(5));
+
+        let new_svc: Either<NewLen, NewUpper> = Either::B(NewUpper);
+        assert_eq!(new_svc.new_service("hello"), Either::B("HELLO".to_string()));
+    }
+
+    #[test]
+    fn test_pointers() {
+        fn new_len<N: NewService<&'static str, Service = usize>>(new_svc: N) -> usize {
+            new_svc.new_service("hello")
+        }
+
+        assert_eq!(new_len(&NewLen), 5);
+        assert_eq!(new_len(Box::new(NewLen)), 5);
+        assert_eq!(new_len(Arc::new(NewLen)), 5);
+
+        // Unsized `NewService`s behind pointers
+        let boxed: Box<dyn NewService<&str, Service = usize>> = Box::new(NewLen);
+        assert_eq!(new_len(&*boxed), 5);
+        assert_eq!(new_len(boxed), 5);
+        let shared: Arc<dyn NewService<&str, Service = usize>> = Arc::new(NewLen);
+        assert_eq!(new_len(shared.clone()), 5);
+        assert_eq!(new_len(shared), 5);
+    }
 }
@@ -0,0 +1,82 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Ready},
+    task::{Context, Poll},
+};
+
+use pipeline_base::Stack;
+use tower::{layer::layer_fn, Service};
+
+use crate::{NewService, NewServiceStack};
+
+/// Turns a `NewService` into a `tower::MakeService`.
+///
+/// The make service is always ready and its futures resolve immediately.
+#[derive(Clone, Debug)]
+pub struct IntoMakeService<N>(N);
+impl<N> IntoMakeService<N> {
+    pub fn new(inner: N) -> Self {
+        Self(inner)
+    }
+
+    pub fn into_inner(self) -> N {
+        self.0
+    }
+}
+impl<N, Tgt> Service<Tgt> for IntoMakeService<N>
+where
+    N: NewService<Tgt>,
+{
+    type Response = N::Service;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, target: Tgt) -> Self::Future {
+        ready(Ok(self.0.new_service(target)))
+    }
+}
+
+impl<N> NewServiceStack<N> {
+    /// Turn the stack into a stack of a `tower::MakeService`.
+    ///
+    /// Prefer `pipeline_make_service::MakeStack::from_new_service`, which returns a `MakeStack` to keep building on.
+    ///
+    /// `Tgt`: the target type of the stack
+    pub fn into_make_service<Tgt>(self) -> Stack<IntoMakeService<N>>
+    where
+        N: NewService<Tgt>,
+    {
+        self.into_inner()
+            .push_named("IntoMakeService", layer_fn(IntoMakeService::new))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::pin};
+
+    use pipeline_test::{assert_ready_ok, noop_context};
+
+    use super::*;
+
+    struct NewLen;
+    impl NewService<&str> for NewLen {
+        type Service = usize;
+        fn new_service(&self, target: &str) -> Self::Service {
+            target.len()
+        }
+    }
+
+    #[test]
+    fn test_into_make_service() {
+        let stack = NewServiceStack::new(Stack::new(NewLen)).into_make_service::<&str>();
+        let mut make_svc = stack.into_inner();
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<&str>::poll_ready(&mut make_svc, cx));
+        let svc = assert_ready_ok!(pin!(make_svc.call("hello")).poll(cx));
+        assert_eq!(svc, 5);
+    }
+}
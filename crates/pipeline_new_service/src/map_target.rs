@@ -0,0 +1,85 @@
+use tower::Layer;
+
+use crate::{NewService, NewServiceStack};
+
+/// Converts the target before handing it to the inner `NewService`.
+///
+/// `F`: a function from the outer target to the inner target
+#[derive(Clone, Debug)]
+pub struct NewMapTarget<N, F> {
+    inner: N,
+    f: F,
+}
+impl<N, F> NewMapTarget<N, F> {
+    pub fn new(inner: N, f: F) -> Self {
+        Self { inner, f }
+    }
+}
+impl<N, F, Tgt, InnerTgt> NewService<Tgt> for NewMapTarget<N, F>
+where
+    F: Fn(Tgt) -> InnerTgt,
+    N: NewService<InnerTgt>,
+{
+    type Service = N::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        self.inner.new_service((self.f)(target))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NewMapTargetLayer<F>(F);
+impl<F> NewMapTargetLayer<F> {
+    pub fn new(f: F) -> Self {
+        Self(f)
+    }
+}
+impl<N, F> Layer<N> for NewMapTargetLayer<F>
+where
+    F: Clone,
+{
+    type Service = NewMapTarget<N, F>;
+    fn layer(&self, inner: N) -> Self::Service {
+        NewMapTarget::new(inner, self.0.clone())
+    }
+}
+
+impl<N> NewServiceStack<N> {
+    /// Convert the target before it reaches the current stack.
+    ///
+    /// `Tgt`: the target type after the layer is applied
+    pub fn push_map_target<Tgt, F>(self, f: F) -> NewServiceStack<NewMapTarget<N, F>>
+    where
+        F: Clone,
+        NewMapTarget<N, F>: NewService<Tgt>,
+    {
+        self.push::<Tgt, _>(NewMapTargetLayer::new(f))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline_base::Stack;
+
+    use super::*;
+
+    /// Builds the target itself.
+    #[derive(Clone)]
+    struct NewIdentity;
+    impl<Tgt> NewService<Tgt> for NewIdentity {
+        type Service = Tgt;
+        fn new_service(&self, target: Tgt) -> Self::Service {
+            target
+        }
+    }
+
+    #[test]
+    fn test_map_target() {
+        let stack = NewServiceStack::new(Stack::new(NewIdentity))
+            .push_map_target::<u16, _>(|port: u16| format!("localhost:{port}"))
+            .check_new_clone::<u16>();
+        let new_svc = stack.into_inner().into_inner();
+
+        assert_eq!(new_svc.new_service(8080), "localhost:8080");
+    }
+}
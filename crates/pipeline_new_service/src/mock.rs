@@ -0,0 +1,82 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use crate::NewService;
+
+/// Create a mock `NewService` and the handle that controls it.
+pub fn new_pair<Tgt, S>() -> (NewMock<Tgt, S>, NewHandle<Tgt, S>) {
+    let state = Arc::new(Mutex::new(NewState {
+        services: VecDeque::new(),
+        targets: VecDeque::new(),
+    }));
+    let mock = NewMock {
+        state: state.clone(),
+    };
+    (mock, NewHandle { state })
+}
+
+struct NewState<Tgt, S> {
+    services: VecDeque<S>,
+    targets: VecDeque<Tgt>,
+}
+
+/// A `NewService` that records its targets and builds the services queued on its `NewHandle`.
+///
+/// Building a service while none is queued panics.
+pub struct NewMock<Tgt, S> {
+    state: Arc<Mutex<NewState<Tgt, S>>>,
+}
+impl<Tgt, S> Clone for NewMock<Tgt, S> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+impl<Tgt, S> NewService<Tgt> for NewMock<Tgt, S> {
+    type Service = S;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        let mut state = self.state.lock().unwrap();
+        state.targets.push_back(target);
+        state
+            .services
+            .pop_front()
+            .expect("no service queued on the mock")
+    }
+}
+
+/// Queues the services a `NewMock` builds and takes the targets it has seen.
+pub struct NewHandle<Tgt, S> {
+    state: Arc<Mutex<NewState<Tgt, S>>>,
+}
+impl<Tgt, S> NewHandle<Tgt, S> {
+    /// Queue the service built for the next target.
+    pub fn push_service(&self, service: S) {
+        self.state.lock().unwrap().services.push_back(service);
+    }
+
+    /// The oldest target not taken yet.
+    pub fn next_target(&self) -> Option<Tgt> {
+        self.state.lock().unwrap().targets.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_mock() {
+        let (new_mock, handle) = new_pair::<&str, usize>();
+        handle.push_service(1);
+        handle.push_service(2);
+        assert_eq!(new_mock.new_service("a"), 1);
+        assert_eq!(new_mock.new_service("b"), 2);
+        assert_eq!(handle.next_target(), Some("a"));
+        assert_eq!(handle.next_target(), Some("b"));
+        assert_eq!(handle.next_target(), None);
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+use crate::NewService;
+
+/// Builds services with a closure.
+#[derive(Clone, Copy)]
+pub struct NewServiceFn<F>(F);
+impl<F, Tgt, S> NewService<Tgt> for NewServiceFn<F>
+where
+    F: Fn(Tgt) -> S,
+{
+    type Service = S;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        (self.0)(target)
+    }
+}
+impl<F> fmt::Debug for NewServiceFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NewServiceFn")
+            .field("f", &std::any::type_name::<F>())
+            .finish()
+    }
+}
+
+/// Create a `NewService` from a closure that builds a service for a target.
+pub fn new_service_fn<F>(f: F) -> NewServiceFn<F> {
+    NewServiceFn(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use pipeline_base::Stack;
+
+    use crate::NewServiceStack;
+
+    use super::*;
+
+    #[test]
+    fn test_new_service_fn() {
+        let stack = Stack::new(new_service_fn(|port: u16| format!("localhost:{port}")));
+        let new_stack = NewServiceStack::new(stack)
+            .push_map_target::<&str, _>(|port: &str| port.parse::<u16>().unwrap())
+            .check_new_clone::<&str>();
+        let new_svc = new_stack.into_inner().into_inner();
+
+        assert_eq!(new_svc.new_service("8080"), "localhost:8080");
+    }
+}
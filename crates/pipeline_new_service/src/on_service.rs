@@ -0,0 +1,140 @@
+use tower::{Layer, Service};
+
+use crate::{NewService, NewServiceStack};
+
+/// `N`: a thing that builds services
+#[derive(Clone, Debug)]
+pub struct NewOnService<L, N> {
+    inner: N,
+    layer: L,
+}
+impl<L, N, Tgt> NewService<Tgt> for NewOnService<L, N>
+where
+    L: Layer<N::Service>,
+    N: NewService<Tgt>,
+{
+    type Service = L::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        let svc = self.inner.new_service(target);
+        self.layer.layer(svc)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NewOnServiceLayer<L>(L);
+impl<L> NewOnServiceLayer<L> {
+    pub fn new(layer: L) -> Self {
+        Self(layer)
+    }
+}
+impl<L, N> Layer<N> for NewOnServiceLayer<L>
+where
+    L: Clone,
+{
+    type Service = NewOnService<L, N>;
+    fn layer(&self, inner: N) -> Self::Service {
+        NewOnService {
+            inner,
+            layer: self.0.clone(),
+        }
+    }
+}
+
+impl<N> NewServiceStack<N> {
+    /// The service returned from `layer` only sees the request, ignoring the target metadata.
+    ///
+    /// The target metadata is passed to the inner service.
+    pub fn push_on_service<Tgt, Req, L>(self, layer: L) -> NewServiceStack<NewOnService<L, N>>
+    where
+        L: Layer<N::Service> + Clone,
+        L::Service: Service<Req>,
+        N: NewService<Tgt>,
+    {
+        let on_service_layer = NewOnServiceLayer::new(layer);
+        self.push::<Tgt, _>(on_service_layer)
+            .check_new_service::<Tgt, Req>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::pin};
+
+    use pipeline_base::Stack;
+    use pipeline_test::{assert_ready_ok, noop_context, EchoService, TraceBody, TraceService};
+    use tower::{layer::layer_fn, Service};
+
+    use super::*;
+
+    /// Wraps every built service in a `TraceService` whose marks start with the target.
+    struct NewTrace<N> {
+        inner: N,
+        req_mark: &'static str,
+        resp_mark: &'static str,
+    }
+    impl<N> NewService<String> for NewTrace<N>
+    where
+        N: NewService<String>,
+    {
+        type Service = TraceService<N::Service>;
+        fn new_service(&self, target: String) -> Self::Service {
+            let svc = self.inner.new_service(target.clone());
+            let req_mark = format!("{target} {}", self.req_mark);
+            let resp_mark = format!("{target} {}", self.resp_mark);
+            TraceService::new(svc, req_mark, resp_mark)
+        }
+    }
+
+    struct NewTraceLayer {
+        req_mark: &'static str,
+        resp_mark: &'static str,
+    }
+    impl<N> Layer<N> for NewTraceLayer {
+        type Service = NewTrace<N>;
+        fn layer(&self, inner: N) -> Self::Service {
+            NewTrace {
+                inner,
+                req_mark: self.req_mark,
+                resp_mark: self.resp_mark,
+            }
+        }
+    }
+
+    struct NewVoid;
+    impl<Tgt> NewService<Tgt> for NewVoid {
+        type Service = ();
+        fn new_service(&self, _: Tgt) -> Self::Service {}
+    }
+
+    #[test]
+    fn test_new() {
+        let stack = Stack::new(NewVoid);
+        let new_stack = NewServiceStack::new(stack)
+            .push_on_service::<String, TraceBody, _>(layer_fn(|_| EchoService))
+            .push::<String, _>(NewTraceLayer {
+                req_mark: "req_1",
+                resp_mark: "resp_1",
+            })
+            .push::<String, _>(NewTraceLayer {
+                req_mark: "req_2",
+                resp_mark: "resp_2",
+            })
+            .check_new_service::<String, TraceBody>();
+        let new_svc = new_stack.into_inner().into_inner();
+
+        let mut svc = new_svc.new_service("target".to_string());
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let resp = assert_ready_ok!(pin!(svc.call(TraceBody::default())).poll(cx));
+        assert_eq!(
+            resp.history,
+            [
+                "target req_2",
+                "target req_1",
+                "target resp_1",
+                "target resp_2"
+            ]
+        );
+    }
+}
@@ -0,0 +1,16 @@
+use pipeline_base::Stack;
+use pipeline_new_service::{NewService, NewServiceStack};
+use pipeline_test::{pair, Mock};
+
+struct NewShared(Mock<String, String>);
+impl NewService<String> for NewShared {
+    type Service = Mock<String, String>;
+    fn new_service(&self, _: String) -> Self::Service {
+        self.0.clone()
+    }
+}
+
+fn main() {
+    let (mock, _handle) = pair::<String, String>();
+    NewServiceStack::new(Stack::new(NewShared(mock))).check_new_service::<String, u16>();
+}
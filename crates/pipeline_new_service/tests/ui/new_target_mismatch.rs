@@ -0,0 +1,15 @@
+use pipeline_base::Stack;
+use pipeline_new_service::{NewService, NewServiceStack};
+use pipeline_test::EchoService;
+
+struct NewEcho;
+impl NewService<String> for NewEcho {
+    type Service = EchoService;
+    fn new_service(&self, _: String) -> Self::Service {
+        EchoService
+    }
+}
+
+fn main() {
+    NewServiceStack::new(Stack::new(NewEcho)).check_new::<u16>();
+}
@@ -0,0 +1,89 @@
+//! Test support for pipelines: mock services, request tracing and readiness assertions.
+
+mod mock;
+mod order;
+mod trace;
+
+use std::task::Context;
+
+pub use mock::{pair, Handle, Mock, ResponseFuture, SendResponse};
+pub use order::{Record, RecordLayer, Recorder};
+pub use trace::{EchoService, Trace, TraceBody, TraceFuture, TraceLayer, TraceService};
+
+/// A context whose waker does nothing, for polling by hand.
+pub fn noop_context() -> Context<'static> {
+    Context::from_waker(futures::task::noop_waker_ref())
+}
+
+/// Assert that a `Poll` is ready and evaluate to its value.
+#[macro_export]
+macro_rules! assert_ready {
+    ($e:expr) => {
+        match $e {
+            ::std::task::Poll::Ready(v) => v,
+            ::std::task::Poll::Pending => panic!("pending; expected ready"),
+        }
+    };
+    ($e:expr, $($msg:tt)+) => {
+        match $e {
+            ::std::task::Poll::Ready(v) => v,
+            ::std::task::Poll::Pending => panic!("pending; expected ready: {}", format_args!($($msg)+)),
+        }
+    };
+}
+
+/// Assert that a `Poll` is pending.
+#[macro_export]
+macro_rules! assert_pending {
+    ($e:expr) => {
+        if let ::std::task::Poll::Ready(v) = $e {
+            panic!("ready {:?}; expected pending", v);
+        }
+    };
+    ($e:expr, $($msg:tt)+) => {
+        if let ::std::task::Poll::Ready(v) = $e {
+            panic!("ready {:?}; expected pending: {}", v, format_args!($($msg)+));
+        }
+    };
+}
+
+/// Assert that a `Poll` is ready with `Ok` and evaluate to the value.
+#[macro_export]
+macro_rules! assert_ready_ok {
+    ($e:expr) => {
+        match $crate::assert_ready!($e) {
+            Ok(v) => v,
+            Err(e) => panic!("error {:?}; expected ok", e),
+        }
+    };
+}
+
+/// Assert that a `Poll` is ready with `Err` and evaluate to the error.
+#[macro_export]
+macro_rules! assert_ready_err {
+    ($e:expr) => {
+        match $crate::assert_ready!($e) {
+            Ok(v) => panic!("ok {:?}; expected error", v),
+            Err(e) => e,
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::task::Poll;
+
+    #[test]
+    fn test_asserts() {
+        assert_eq!(assert_ready!(Poll::Ready(1)), 1);
+        assert_pending!(Poll::<u8>::Pending);
+        assert_eq!(assert_ready_ok!(Poll::Ready(Ok::<_, ()>(2))), 2);
+        assert_eq!(assert_ready_err!(Poll::Ready(Err::<(), _>(3))), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected ready: poll_ready")]
+    fn test_assert_ready_pending() {
+        assert_ready!(Poll::<u8>::Pending, "poll_ready");
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use tokio::sync::oneshot;
+use tower::{BoxError, Service};
+
+/// Create a mock service and the handle that controls it.
+///
+/// A mock whose response is itself a service, such as `pair::<Tgt, Mock<Req, Resp>>()`, is a mock `MakeService`.
+///
+/// The mock is ready until the handle limits it with `Handle::allow`.
+pub fn pair<Req, Resp>() -> (Mock<Req, Resp>, Handle<Req, Resp>) {
+    let state = Arc::new(Mutex::new(State {
+        allowed: None,
+        error: None,
+        requests: VecDeque::new(),
+        waker: None,
+    }));
+    let mock = Mock {
+        state: state.clone(),
+    };
+    (mock, Handle { state })
+}
+
+struct State<Req, Resp> {
+    /// The number of calls left before the mock becomes pending; unlimited if `None`
+    allowed: Option<usize>,
+    /// The error the next `poll_ready` fails with
+    error: Option<BoxError>,
+    requests: VecDeque<(Req, SendResponse<Resp>)>,
+    waker: Option<Waker>,
+}
+
+/// A service whose readiness and responses are controlled by its `Handle`.
+///
+/// Clones share the same handle.
+pub struct Mock<Req, Resp> {
+    state: Arc<Mutex<State<Req, Resp>>>,
+}
+impl<Req, Resp> Clone for Mock<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+impl<Req, Resp> Service<Req> for Mock<Req, Resp> {
+    type Response = Resp;
+    type Error = BoxError;
+    type Future = ResponseFuture<Resp>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(error) = state.error.take() {
+            return Poll::Ready(Err(error));
+        }
+        if state.allowed == Some(0) {
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        let mut state = self.state.lock().unwrap();
+        if let Some(allowed) = state.allowed.as_mut() {
+            *allowed = allowed
+                .checked_sub(1)
+                .expect("called a mock that was not ready");
+        }
+        let (tx, rx) = oneshot::channel();
+        state.requests.push_back((req, SendResponse(tx)));
+        ResponseFuture(rx)
+    }
+}
+
+/// Controls the readiness of a `Mock` and responds to its requests.
+pub struct Handle<Req, Resp> {
+    state: Arc<Mutex<State<Req, Resp>>>,
+}
+impl<Req, Resp> Handle<Req, Resp> {
+    /// Let the mock be ready for `n` more calls.
+    pub fn allow(&self, n: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.allowed = Some(n);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Let the mock be ready for any number of calls.
+    pub fn allow_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.allowed = None;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Fail the next `poll_ready` of the mock with `error`.
+    pub fn fail_ready(&self, error: impl Into<BoxError>) {
+        let mut state = self.state.lock().unwrap();
+        state.error = Some(error.into());
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// The oldest request not taken yet, with the means to respond to it.
+    pub fn next_request(&self) -> Option<(Req, SendResponse<Resp>)> {
+        self.state.lock().unwrap().requests.pop_front()
+    }
+}
+
+/// Responds to a request of a `Mock`.
+///
+/// Dropping it fails the response.
+pub struct SendResponse<Resp>(oneshot::Sender<Result<Resp, BoxError>>);
+impl<Resp> SendResponse<Resp> {
+    pub fn send_response(self, resp: Resp) {
+        let _ = self.0.send(Ok(resp));
+    }
+
+    pub fn send_error(self, error: impl Into<BoxError>) {
+        let _ = self.0.send(Err(error.into()));
+    }
+}
+
+/// Resolves once the handle responds to the request.
+pub struct ResponseFuture<Resp>(oneshot::Receiver<Result<Resp, BoxError>>);
+impl<Resp> Future for ResponseFuture<Resp> {
+    type Output = Result<Resp, BoxError>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.0).poll(cx) {
+            Poll::Ready(Ok(res)) => Poll::Ready(res),
+            Poll::Ready(Err(_)) => Poll::Ready(Err("mock response dropped".into())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::pin_mut;
+
+    use super::*;
+    use crate::{assert_pending, assert_ready_err, assert_ready_ok, noop_context};
+
+    #[test]
+    fn test_mock() {
+        let (mut mock, handle) = pair::<&str, usize>();
+        let cx = &mut noop_context();
+
+        // Limit the readiness.
+        handle.allow(1);
+        assert_ready_ok!(mock.poll_ready(cx));
+        let fut = mock.call("hello");
+        assert_pending!(mock.poll_ready(cx));
+        handle.allow_all();
+        assert_ready_ok!(mock.poll_ready(cx));
+
+        // Respond to the request.
+        pin_mut!(fut);
+        assert_pending!(fut.as_mut().poll(cx));
+        let (req, send) = handle.next_request().unwrap();
+        assert_eq!(req, "hello");
+        send.send_response(req.len());
+        assert_eq!(assert_ready_ok!(fut.as_mut().poll(cx)), 5);
+
+        // Fail the readiness and a response.
+        handle.fail_ready("not ready");
+        let error = assert_ready_err!(mock.poll_ready(cx));
+        assert_eq!(error.to_string(), "not ready");
+        let fut = mock.call("world");
+        pin_mut!(fut);
+        drop(handle.next_request());
+        let error = assert_ready_err!(fut.as_mut().poll(cx));
+        assert_eq!(error.to_string(), "mock response dropped");
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use tower::{Layer, Service};
+
+/// Records the order in which the layers of a stack are reached, whatever the request type.
+///
+/// Clones share the same record.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder(Arc<Mutex<Vec<String>>>);
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A layer that records `name` every time it is called.
+    pub fn layer(&self, name: impl Into<String>) -> RecordLayer {
+        RecordLayer {
+            recorder: self.clone(),
+            name: name.into(),
+        }
+    }
+
+    /// Take the names recorded so far.
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    /// Assert the names recorded so far and clear them.
+    #[track_caller]
+    pub fn assert_order(&self, expected: &[&str]) {
+        assert_eq!(self.take(), expected);
+    }
+
+    fn record(&self, name: &str) {
+        self.0.lock().unwrap().push(name.to_string());
+    }
+}
+
+/// Records its name on every `call` of a service, or every `new_service` of a `NewService` with the `new-service` feature.
+#[derive(Clone, Debug)]
+pub struct Record<S> {
+    inner: S,
+    recorder: Recorder,
+    name: String,
+}
+impl<S, Req> Service<Req> for Record<S>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        self.recorder.record(&self.name);
+        self.inner.call(req)
+    }
+}
+#[cfg(feature = "new-service")]
+impl<N, Tgt> pipeline_new_service::NewService<Tgt> for Record<N>
+where
+    N: pipeline_new_service::NewService<Tgt>,
+{
+    type Service = N::Service;
+
+    fn new_service(&self, target: Tgt) -> Self::Service {
+        self.recorder.record(&self.name);
+        self.inner.new_service(target)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordLayer {
+    recorder: Recorder,
+    name: String,
+}
+impl<S> Layer<S> for RecordLayer {
+    type Service = Record<S>;
+    fn layer(&self, inner: S) -> Self::Service {
+        Record {
+            inner,
+            recorder: self.recorder.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::pin_mut;
+    use std::future::Future;
+
+    use super::*;
+    use crate::{assert_ready_ok, noop_context, EchoService};
+
+    #[test]
+    fn test_record() {
+        let recorder = Recorder::new();
+        let mut svc = recorder
+            .layer("outer")
+            .layer(recorder.layer("inner").layer(EchoService));
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<()>::poll_ready(&mut svc, cx));
+        let fut = svc.call(());
+        pin_mut!(fut);
+        assert_ready_ok!(fut.as_mut().poll(cx));
+        recorder.assert_order(&["outer", "inner"]);
+        recorder.assert_order(&[]);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    convert::Infallible,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tower::{Layer, Service};
+
+/// A request or response that records the layers it passes through.
+pub trait Trace {
+    fn history_mut(&mut self) -> &mut Vec<String>;
+}
+
+/// A request and response that is only a history.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TraceBody {
+    pub history: Vec<String>,
+}
+impl Trace for TraceBody {
+    fn history_mut(&mut self) -> &mut Vec<String> {
+        &mut self.history
+    }
+}
+
+/// Records its marks in the history of the request on the way in and of the response on the way out.
+#[derive(Clone, Debug)]
+pub struct TraceService<S> {
+    inner: S,
+    req_mark: String,
+    resp_mark: String,
+}
+impl<S> TraceService<S> {
+    pub fn new(inner: S, req_mark: impl Into<String>, resp_mark: impl Into<String>) -> Self {
+        Self {
+            inner,
+            req_mark: req_mark.into(),
+            resp_mark: resp_mark.into(),
+        }
+    }
+}
+impl<S, Req> Service<Req> for TraceService<S>
+where
+    Req: Trace,
+    S: Service<Req>,
+    S::Response: Trace,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TraceFuture<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, mut req: Req) -> Self::Future {
+        req.history_mut().push(self.req_mark.clone());
+        TraceFuture {
+            future: self.inner.call(req),
+            resp_mark: Some(self.resp_mark.clone()),
+        }
+    }
+}
+
+pin_project! {
+    pub struct TraceFuture<F> {
+        #[pin]
+        future: F,
+        resp_mark: Option<String>,
+    }
+}
+impl<F, T, E> Future for TraceFuture<F>
+where
+    F: Future<Output = Result<T, E>>,
+    T: Trace,
+{
+    type Output = Result<T, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut resp = match this.future.poll(cx) {
+            Poll::Ready(Ok(resp)) => resp,
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        let resp_mark = this.resp_mark.take().expect("polled after completion");
+        resp.history_mut().push(resp_mark);
+        Poll::Ready(Ok(resp))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TraceLayer {
+    req_mark: String,
+    resp_mark: String,
+}
+impl TraceLayer {
+    pub fn new(req_mark: impl Into<String>, resp_mark: impl Into<String>) -> Self {
+        Self {
+            req_mark: req_mark.into(),
+            resp_mark: resp_mark.into(),
+        }
+    }
+}
+impl<S> Layer<S> for TraceLayer {
+    type Service = TraceService<S>;
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceService::new(inner, self.req_mark.clone(), self.resp_mark.clone())
+    }
+}
+
+/// Responds with the request.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EchoService;
+impl<Req> Service<Req> for EchoService {
+    type Response = Req;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, req: Req) -> Self::Future {
+        ready(Ok(req))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::pin_mut;
+    use tower::ServiceBuilder;
+
+    use super::*;
+    use crate::{assert_ready_ok, noop_context};
+
+    #[test]
+    fn test_trace() {
+        let mut svc = ServiceBuilder::new()
+            .layer(TraceLayer::new("req_1", "resp_1"))
+            .layer(TraceLayer::new("req_2", "resp_2"))
+            .service(EchoService);
+
+        let cx = &mut noop_context();
+        assert_ready_ok!(Service::<TraceBody>::poll_ready(&mut svc, cx));
+        let fut = svc.call(TraceBody::default());
+        pin_mut!(fut);
+        let resp = assert_ready_ok!(fut.as_mut().poll(cx));
+        assert_eq!(resp.history, ["req_1", "req_2", "resp_2", "resp_1"]);
+    }
+}